@@ -0,0 +1,268 @@
+use core::{cmp::Ordering, fmt};
+
+/// Results with more bits than this are overflows, since they'd take too long
+/// to calculate and print.
+pub const MAX_BITS: u64 = 1 << 18;
+
+/// An integer of any size, as a sign and base 2^32 digits, least significant
+/// first and without leading zeros.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+impl From<i128> for BigInt {
+    fn from(n: i128) -> Self {
+        let mut magnitude = n.unsigned_abs();
+        let mut digits = Vec::new();
+        while magnitude > 0 {
+            digits.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        BigInt {
+            negative: n < 0,
+            digits,
+        }
+    }
+}
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        BigInt::from(n as i128)
+    }
+}
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        // Groups of nine decimal digits, least significant first.
+        let mut groups = Vec::new();
+        let mut rest = self.digits.clone();
+        while !rest.is_empty() {
+            let (quotient, remainder) = div_small(&rest, 1_000_000_000);
+            groups.push(remainder);
+            rest = quotient;
+        }
+        match groups.split_last() {
+            Some((first, others)) => {
+                write!(f, "{}", first)?;
+                others.iter().rev().try_for_each(|g| write!(f, "{:09}", g))
+            }
+            None => write!(f, "0"),
+        }
+    }
+}
+impl fmt::LowerHex for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_digits(f, |d| format!("{:x}", d), |d| format!("{:08x}", d))
+    }
+}
+impl fmt::Binary for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_digits(f, |d| format!("{:b}", d), |d| format!("{:032b}", d))
+    }
+}
+impl BigInt {
+    fn new(negative: bool, mut digits: Vec<u32>) -> BigInt {
+        trim(&mut digits);
+        BigInt {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::new(false, self.digits.clone())
+    }
+
+    pub fn negate(self) -> BigInt {
+        BigInt::new(!self.negative, self.digits)
+    }
+
+    /// Bits in the magnitude, 0 for zero.
+    pub fn bits(&self) -> u64 {
+        match self.digits.last() {
+            Some(top) => self.digits.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0i128, |m, &d| (m << 32) | d as i128);
+        i64::try_from(if self.negative { -magnitude } else { magnitude }).ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0.0, |m, &d| m * 4294967296.0 + d as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add(&self.digits, &other.digits));
+        }
+        match compare(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, sub(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub(&self.digits, &other.digits)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.clone().negate())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let t = a as u64 * b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigInt::new(self.negative != other.negative, digits)
+    }
+
+    /// The quotient rounded toward zero and the remainder, which has the sign
+    /// of `self`. `None` when `other` is zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        let (quotient, remainder) = match other.digits.as_slice() {
+            [] => return None,
+            &[d] => {
+                let (quotient, remainder) = div_small(&self.digits, d);
+                (quotient, vec![remainder])
+            }
+            // Shift and subtract, a bit at a time.
+            divisor => {
+                let mut quotient = vec![0u32; self.digits.len()];
+                let mut remainder = Vec::new();
+                for bit in (0..self.bits() as usize).rev() {
+                    let next = (self.digits[bit / 32] >> (bit % 32)) & 1;
+                    remainder = add(&remainder, &remainder);
+                    remainder = add(&remainder, &[next]);
+                    if compare(&remainder, divisor) != Ordering::Less {
+                        remainder = sub(&remainder, divisor);
+                        quotient[bit / 32] |= 1 << (bit % 32);
+                    }
+                }
+                (quotient, remainder)
+            }
+        };
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, mut exp: u32) -> BigInt {
+        let (mut base, mut result) = (self.clone(), BigInt::from(1i64));
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// Writes the magnitude with `top` for the leading digit and `padded` for
+    /// the rest.
+    fn write_digits(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        top: fn(u32) -> String,
+        padded: fn(u32) -> String,
+    ) -> fmt::Result {
+        match self.digits.split_last() {
+            Some((first, others)) => {
+                write!(f, "{}", top(*first))?;
+                others
+                    .iter()
+                    .rev()
+                    .try_for_each(|&d| write!(f, "{}", padded(d)))
+            }
+            None => write!(f, "0"),
+        }
+    }
+}
+
+fn trim(digits: &mut Vec<u32>) {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+}
+
+fn compare(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut digits = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &d) in long.iter().enumerate() {
+        let t = d as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        digits.push(t as u32);
+        carry = t >> 32;
+    }
+    digits.push(carry as u32);
+    trim(&mut digits);
+    digits
+}
+
+/// `a - b`, `a` being at least `b`.
+fn sub(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &d) in a.iter().enumerate() {
+        let mut t = d as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+        borrow = (t < 0) as i64;
+        if t < 0 {
+            t += 1 << 32;
+        }
+        digits.push(t as u32);
+    }
+    trim(&mut digits);
+    digits
+}
+
+/// Quotient and remainder of a magnitude by a single digit.
+fn div_small(digits: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; digits.len()];
+    let mut remainder = 0u64;
+    for (i, &d) in digits.iter().enumerate().rev() {
+        let t = (remainder << 32) | d as u64;
+        quotient[i] = (t / divisor as u64) as u32;
+        remainder = t % divisor as u64;
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
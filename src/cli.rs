@@ -0,0 +1,95 @@
+pub const USAGE: &str = "Usage:
+  sya                   start the interactive prompt, or evaluate piped stdin line by line
+  sya <expression>...   evaluate each expression
+  sya -f <file>         evaluate each line of a file
+  sya run <file> [-v]   run a script, printing its final value or every value with -v
+  sya --json ...        print one JSON object per input instead of the result
+  sya --no-config ...   ignore the config and units files
+  sya --hex, --bin ...  show integers in hexadecimal or binary
+  sya --group ...       group digits by thousands
+  sya --types ...       show the type of each result
+  sya --decimals ...    show exact values as decimals rather than fractions
+  sya -h, --help        show this message
+
+Exit codes: 0 on success, 1 on usage or IO errors, 2 on parse errors, 3 on evaluation errors";
+
+use crate::format::Radix;
+
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    /// Interactive prompt when stdin is a terminal, lines from stdin otherwise.
+    Stdin,
+    Args(Vec<String>),
+    File(String),
+    /// A script, stopping at the first error.
+    Script(String),
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Options {
+    pub json: bool,
+    /// Print every value of a script, not only the last one.
+    pub verbose: bool,
+    pub no_config: bool,
+    /// Display flags, applied over the config.
+    pub radix: Option<Radix>,
+    pub group: bool,
+    pub types: bool,
+    pub decimals: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Source, Options),
+    Help,
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.peekable();
+    let mut expressions = Vec::new();
+    let mut file = None;
+    let mut script = None;
+    // `run` is the first argument that isn't a flag, and the script is the
+    // next one, so flags can go on either side.
+    let mut run = false;
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--json" => options.json = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "--no-config" => options.no_config = true,
+            "--hex" => options.radix = Some(Radix::Hex),
+            "--bin" => options.radix = Some(Radix::Bin),
+            "--group" => options.group = true,
+            "--types" => options.types = true,
+            "--decimals" => options.decimals = true,
+            "-f" | "--file" => match args.next() {
+                Some(path) => file = Some(path),
+                None => return Err(format!("Missing file after '{}'", arg)),
+            },
+            // Everything after `--` is an expression, even `-f`.
+            "--" => expressions.extend(args.by_ref()),
+            "run" if !run && expressions.is_empty() => run = true,
+            _ if run && script.is_none() => script = Some(arg),
+            _ => expressions.push(arg),
+        }
+    }
+    if run && script.is_none() {
+        return Err("Missing file after 'run'".to_string());
+    }
+
+    let source = match (script, file, expressions.is_empty()) {
+        (Some(_), _, false) | (Some(_), Some(_), _) => {
+            return Err("A script can't be run with other inputs".to_string())
+        }
+        (Some(path), None, true) => Source::Script(path),
+        (None, Some(_), false) => {
+            return Err("Can't evaluate both a file and expressions".to_string())
+        }
+        (None, Some(path), true) => Source::File(path),
+        (None, None, false) => Source::Args(expressions),
+        (None, None, true) => Source::Stdin,
+    };
+    Ok(Command::Run(source, options))
+}
@@ -0,0 +1,153 @@
+use std::{env, io::IsTerminal};
+
+use crate::{
+    sya::split_statements,
+    tokenizer::{Token, Tokenizer},
+    units::Units,
+};
+
+const RESET: &str = "\x1b[0m";
+const COMMAND: &str = "32";
+const NUMBER: &str = "36";
+const OPERATOR: &str = "33";
+const FUNCTION: &str = "34";
+const REFERENCE: &str = "35";
+const COMMENT: &str = "2";
+const PAREN: &str = "1";
+const MATCHING: &str = "1;7";
+const ERROR: &str = "1;31";
+const REGION: &str = "1;4;31";
+
+/// Whether to colour `stream`: only terminals, and never when `NO_COLOR` is
+/// set.
+pub fn enabled<S: IsTerminal>(stream: &S) -> bool {
+    stream.is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+pub fn paint(style: &str, text: &str) -> String {
+    format!("\x1b[{}m{}{}", style, text, RESET)
+}
+
+pub fn error(text: &str) -> String {
+    paint(ERROR, text)
+}
+
+/// Colours `line` by token, with the paren next to `cursor` and its match
+/// highlighted. Unmatched parens and invalid characters are red, and only
+/// names of `units` are units.
+pub fn highlight(line: &str, cursor: Option<usize>, units: &Units) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut styles: Vec<Option<&str>> = vec![None; chars.len()];
+
+    let command = match line.starts_with(':') || line == "q!" {
+        true => chars.iter().take_while(|c| !c.is_whitespace()).count(),
+        false => 0,
+    };
+    styles[..command].fill(Some(COMMAND));
+
+    let mut offset = command;
+    for statement in split_statements(&line[byte_index(line, command)..]) {
+        let mut tokenizer = Tokenizer::new(statement).with_units(units);
+        let failed = tokenizer.parse().is_err();
+        for (token, (start, end)) in tokenizer.spanned() {
+            let style = match token {
+                Token::Number(_) | Token::Date(_) | Token::Unit(_) => NUMBER,
+                Token::Operator(_)
+                | Token::UNARY(_)
+                | Token::ASSIGN
+                | Token::COMMA
+                | Token::COLON
+                | Token::Convert(_) => OPERATOR,
+                Token::Function(_) | Token::Call(..) => FUNCTION,
+                Token::Ident(i) if i.starts_with('$') => REFERENCE,
+                Token::OPEN | Token::CLOSE | Token::LBRACKET | Token::RBRACKET => PAREN,
+                Token::Ident(_) => continue,
+            };
+            styles[offset + start..offset + end].fill(Some(style));
+        }
+        if failed {
+            let (start, end) = tokenizer.span();
+            let end = (offset + end).min(chars.len());
+            styles[(offset + start).min(end)..end].fill(Some(ERROR));
+        }
+        offset += statement.chars().count() + 1;
+    }
+
+    let code = match chars.iter().skip(command).position(|&c| c == '#') {
+        Some(start) => command + start,
+        None => chars.len(),
+    };
+    styles[code..].fill(Some(COMMENT));
+
+    let matches = match_parens(&chars[..code]);
+    for (i, m) in matches.iter().enumerate() {
+        if matches!(chars[i], '(' | ')') && m.is_none() {
+            styles[i] = Some(ERROR);
+        }
+    }
+    let near = cursor.map_or(vec![], |c| vec![c, c.wrapping_sub(1)]);
+    if let Some(&i) = near
+        .iter()
+        .find(|&&i| matches.get(i).is_some_and(Option::is_some))
+    {
+        styles[i] = Some(MATCHING);
+        styles[matches[i].unwrap()] = Some(MATCHING);
+    }
+
+    render(&chars, &styles)
+}
+
+/// `line` with the characters in `span` underlined in red.
+pub fn mark(line: &str, span: (usize, usize)) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut styles: Vec<Option<&str>> = vec![None; chars.len()];
+    let end = span.1.min(chars.len());
+    styles[span.0.min(end)..end].fill(Some(REGION));
+    render(&chars, &styles)
+}
+
+/// Index of the matching paren of each paren.
+fn match_parens(chars: &[char]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; chars.len()];
+    let mut open = Vec::new();
+    for (i, c) in chars.iter().enumerate() {
+        match c {
+            '(' => open.push(i),
+            ')' => {
+                if let Some(j) = open.pop() {
+                    matches[i] = Some(j);
+                    matches[j] = Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    matches
+}
+
+fn render(chars: &[char], styles: &[Option<&str>]) -> String {
+    let mut out = String::new();
+    let mut current = None;
+    for (c, style) in chars.iter().zip(styles) {
+        if *style != current {
+            if current.is_some() {
+                out.push_str(RESET);
+            }
+            if let Some(style) = style {
+                out.push_str(&format!("\x1b[{}m", style));
+            }
+            current = *style;
+        }
+        out.push(*c);
+    }
+    if current.is_some() {
+        out.push_str(RESET);
+    }
+    out
+}
+
+fn byte_index(line: &str, chars: usize) -> usize {
+    line.char_indices()
+        .nth(chars)
+        .map_or(line.len(), |(i, _)| i)
+}
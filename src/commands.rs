@@ -0,0 +1,199 @@
+use crate::{
+    format::Radix,
+    number::{Angle, Mode, Rounding},
+    solve::SolverOptions,
+};
+
+/// Every meta-command with its arguments and description, for `:help` and
+/// suggestions.
+pub const COMMANDS: &[(&str, &str, &str)] = &[
+    (":help", "", "show this message"),
+    (":quit", "", "exit, also q!"),
+    (":rpn", "on|off", "show the RPN of each input"),
+    (":vars", "", "list the variables and functions"),
+    (":clear", "", "remove every variable, function and result"),
+    (":history", "", "list the results, usable as $1, $2..."),
+    (
+        ":mode",
+        "auto|int|float|exact",
+        "how numbers are calculated",
+    ),
+    (
+        ":precision",
+        "N|off",
+        "digits shown after the decimal point",
+    ),
+    (":angle", "rad|deg|grad", "unit of angles"),
+    (
+        ":rounding",
+        "half-up|half-even|half-away|toward-zero|floor|ceil",
+        "how round and roundto settle ties",
+    ),
+    (":tolerance", "X", "relative tolerance of solve and root"),
+    (
+        ":iterations",
+        "N",
+        "steps solve and root take before giving up",
+    ),
+    (":hex", "", "show integers in hexadecimal"),
+    (":bin", "", "show integers in binary"),
+    (":dec", "", "show integers in decimal"),
+    (":group", "on|off", "group digits by thousands"),
+    (":types", "on|off", "show the type of each result"),
+    (":fractions", "on|off", "show exact values as fractions"),
+    (":trace", "<expression>", "show each step of the algorithm"),
+    (
+        ":simplify",
+        "<expression>",
+        "fold constants and remove identities",
+    ),
+    (
+        ":simplify!",
+        "<expression>",
+        "also apply identities unsafe for NaN",
+    ),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Command<'a> {
+    Help,
+    Quit,
+    Rpn(bool),
+    Vars,
+    Clear,
+    History,
+    Mode(Mode),
+    Precision(Option<usize>),
+    Angle(Angle),
+    Rounding(Rounding),
+    Tolerance(f64),
+    Iterations(usize),
+    Radix(Radix),
+    Group(bool),
+    Types(bool),
+    Fractions(bool),
+    /// Prints the current value of a setting, given without argument.
+    Show(&'a str),
+    Trace(&'a str),
+    Simplify(&'a str, bool),
+}
+
+/// Parses a meta-command, `Ok(None)` when `input` is an expression.
+pub fn parse_command(input: &str) -> Result<Option<Command<'_>>, String> {
+    if input == "q!" {
+        return Ok(Some(Command::Quit));
+    }
+    if !input.starts_with(':') {
+        return Ok(None);
+    }
+
+    let (name, arg) = match input.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (input, ""),
+    };
+    let command = match (name, arg) {
+        (":help", "") => Command::Help,
+        (":quit", "") => Command::Quit,
+        (":rpn", "on") => Command::Rpn(true),
+        (":rpn", "off") => Command::Rpn(false),
+        (":vars", "") => Command::Vars,
+        (":clear", "") => Command::Clear,
+        (":history", "") => Command::History,
+        (":mode" | ":precision" | ":angle" | ":rounding" | ":tolerance" | ":iterations", "") => {
+            Command::Show(name)
+        }
+        (":mode", mode) => match Mode::parse(mode) {
+            Some(mode) => Command::Mode(mode),
+            None => return Err(usage(name)),
+        },
+        (":precision", "off") => Command::Precision(None),
+        (":precision", n) => match n.parse() {
+            Ok(n) => Command::Precision(Some(n)),
+            Err(_) => return Err(usage(name)),
+        },
+        (":angle", angle) => match Angle::parse(angle) {
+            Some(angle) => Command::Angle(angle),
+            None => return Err(usage(name)),
+        },
+        (":rounding", rounding) => match Rounding::parse(rounding) {
+            Some(rounding) => Command::Rounding(rounding),
+            None => return Err(usage(name)),
+        },
+        (":tolerance", t) => match SolverOptions::parse_tolerance(t) {
+            Some(t) => Command::Tolerance(t),
+            None => return Err(usage(name)),
+        },
+        (":iterations", n) => match SolverOptions::parse_iterations(n) {
+            Some(n) => Command::Iterations(n),
+            None => return Err(usage(name)),
+        },
+        (":hex", "") => Command::Radix(Radix::Hex),
+        (":bin", "") => Command::Radix(Radix::Bin),
+        (":dec", "") => Command::Radix(Radix::Dec),
+        (":group", "on") => Command::Group(true),
+        (":group", "off") => Command::Group(false),
+        (":types", "on") => Command::Types(true),
+        (":types", "off") => Command::Types(false),
+        (":fractions", "on") => Command::Fractions(true),
+        (":fractions", "off") => Command::Fractions(false),
+        (":trace", expr) => Command::Trace(expr),
+        (":simplify", expr) => Command::Simplify(expr, false),
+        (":simplify!", expr) => Command::Simplify(expr, true),
+        _ if COMMANDS.iter().any(|(c, _, _)| *c == name) => return Err(usage(name)),
+        _ => return Err(unknown(name)),
+    };
+    Ok(Some(command))
+}
+
+pub fn help() -> String {
+    let lines: Vec<String> = COMMANDS
+        .iter()
+        .map(|(name, args, about)| format!("  {:<32}{}", format!("{} {}", name, args), about))
+        .collect();
+    format!("Commands:\n{}", lines.join("\n"))
+}
+
+fn usage(name: &str) -> String {
+    match COMMANDS.iter().find(|(c, _, _)| *c == name) {
+        Some((name, "", _)) => format!("Usage: {}", name),
+        Some((name, args, _)) => format!("Usage: {} {}", name, args),
+        None => unknown(name),
+    }
+}
+
+/// Suggests the commands starting with `name`, or else within two edits of
+/// it.
+fn unknown(name: &str) -> String {
+    let names = COMMANDS.iter().map(|(c, _, _)| *c);
+    let mut suggestions: Vec<&str> = names
+        .clone()
+        .filter(|c| name.len() > 1 && c.starts_with(name))
+        .collect();
+    if suggestions.is_empty() {
+        suggestions = names.filter(|c| distance(name, c) <= 2).collect();
+    }
+    match suggestions.as_slice() {
+        [] => format!("Unknown command '{}', see :help", name),
+        _ => format!(
+            "Unknown command '{}', did you mean {}?",
+            name,
+            suggestions.join(" or ")
+        ),
+    }
+}
+
+/// Levenshtein distance.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,160 @@
+use std::{env, fs, io, path::PathBuf};
+
+use crate::{
+    format::{Format, Radix},
+    number::{Angle, Mode, Rounding},
+    solve::SolverOptions,
+};
+
+/// Defaults read at startup from `config_dir()/config`, like:
+///
+/// ```text
+/// mode = exact
+/// precision = 4
+/// angle = deg
+/// rounding = half-even
+/// tolerance = 1e-10
+/// iterations = 200
+/// rpn = off
+/// prompt = "sya> "
+/// color = auto
+/// radix = dec
+/// group = on
+/// types = off
+/// fractions = on
+///
+/// [definitions]
+/// g = 9.81
+/// kmh(ms) = ms * 3.6
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub mode: Mode,
+    pub format: Format,
+    pub angle: Angle,
+    pub rounding: Rounding,
+    pub solver: SolverOptions,
+    pub echo_rpn: bool,
+    pub prompt: String,
+    /// Forces colour on or off, `None` colours terminals only.
+    pub color: Option<bool>,
+    /// Statements run before any input, to define variables and functions.
+    pub definitions: Vec<String>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::default(),
+            format: Format::default(),
+            angle: Angle::default(),
+            rounding: Rounding::default(),
+            solver: SolverOptions::default(),
+            echo_rpn: true,
+            prompt: "> ".to_string(),
+            color: None,
+            definitions: Vec::new(),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/sya`, falling back to `~/.config/sya`, or `%APPDATA%\sya`
+/// on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("sya"))
+}
+
+pub fn path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config"))
+}
+
+/// Reads the config file, the defaults when there's none.
+pub fn load() -> Result<Config, String> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => parse(&content).map_err(|e| format!("{}:{}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut definitions = false;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            match line {
+                "[definitions]" => definitions = true,
+                _ => return Err(format!("{}: Unknown section '{}'", number + 1, line)),
+            }
+            continue;
+        }
+        if definitions {
+            config.definitions.push(line.to_string());
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(format!("{}: Expected 'setting = value'", number + 1)),
+        };
+        let invalid = || format!("{}: Invalid value '{}' for '{}'", number + 1, value, key);
+        match key {
+            "mode" => config.mode = Mode::parse(value).ok_or_else(invalid)?,
+            "precision" => {
+                config.format.precision = match value {
+                    "off" => None,
+                    n => Some(n.parse().map_err(|_| invalid())?),
+                }
+            }
+            "radix" => config.format.radix = Radix::parse(value).ok_or_else(invalid)?,
+            "group" => config.format.group = switch(value).ok_or_else(invalid)?,
+            "types" => config.format.types = switch(value).ok_or_else(invalid)?,
+            "fractions" => config.format.fractions = switch(value).ok_or_else(invalid)?,
+            "angle" => config.angle = Angle::parse(value).ok_or_else(invalid)?,
+            "rounding" => config.rounding = Rounding::parse(value).ok_or_else(invalid)?,
+            "tolerance" => {
+                config.solver.tolerance =
+                    SolverOptions::parse_tolerance(value).ok_or_else(invalid)?
+            }
+            "iterations" => {
+                config.solver.max_iterations =
+                    SolverOptions::parse_iterations(value).ok_or_else(invalid)?
+            }
+            "rpn" => config.echo_rpn = switch(value).ok_or_else(invalid)?,
+            "prompt" => {
+                config.prompt = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    Some(quoted) => quoted.to_string(),
+                    None => value.to_string(),
+                }
+            }
+            "color" => {
+                config.color = match value {
+                    "auto" => None,
+                    v => Some(switch(v).ok_or_else(invalid)?),
+                }
+            }
+            _ => return Err(format!("{}: Unknown setting '{}'", number + 1, key)),
+        }
+    }
+    Ok(config)
+}
+
+fn switch(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,26 @@
+use std::f64::consts;
+
+/// Named constants, read-only and resolved before variables.
+pub const CONSTANTS: &[(&str, f64, &str)] = &[
+    (
+        "pi",
+        consts::PI,
+        "Ratio of a circle's circumference to its diameter",
+    ),
+    ("e", consts::E, "Base of the natural logarithm"),
+    ("tau", consts::TAU, "Full turn in radians, 2 * pi"),
+    (
+        "phi",
+        1.618_033_988_749_895,
+        "Golden ratio, (1 + sqrt(5)) / 2",
+    ),
+    ("inf", f64::INFINITY, "Positive infinity"),
+    ("nan", f64::NAN, "Not a number"),
+];
+
+pub fn get(name: &str) -> Option<f64> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, ..)| *constant == name)
+        .map(|(_, value, _)| *value)
+}
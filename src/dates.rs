@@ -0,0 +1,190 @@
+use core::fmt;
+
+use crate::{errors::SyaError, number::Number, units::Quantity, value::Value};
+
+/// A point in time, as seconds since 1970-01-01 UTC. Dates are shown at their
+/// fixed UTC offset, when they were written with one.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Date {
+    seconds: i64,
+    offset: Option<i64>,
+    time: bool,
+}
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = self.seconds + self.offset.unwrap_or(0);
+        let (year, month, day) = civil(local.div_euclid(86400));
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+        let time = local.rem_euclid(86400);
+        if !self.time && time == 0 {
+            return Ok(());
+        }
+        write!(f, "T{:02}:{:02}", time / 3600, time % 3600 / 60)?;
+        if time % 60 != 0 {
+            write!(f, ":{:02}", time % 60)?;
+        }
+        match self.offset {
+            Some(0) => write!(f, "Z"),
+            Some(o) => {
+                let sign = if o < 0 { '-' } else { '+' };
+                write!(
+                    f,
+                    "{}{:02}:{:02}",
+                    sign,
+                    o.abs() / 3600,
+                    o.abs() % 3600 / 60
+                )
+            }
+            None => Ok(()),
+        }
+    }
+}
+impl Date {
+    /// Adding and subtracting durations moves a date, and subtracting dates
+    /// gives the duration between them.
+    pub fn binary(self, sign: char, other: Value) -> Result<Value, SyaError> {
+        match (sign, other) {
+            ('-', Value::Date(other)) => Ok(Value::Quantity(Box::new(Quantity::duration(
+                self.seconds - other.seconds,
+            )))),
+            ('+' | '-', Value::Quantity(q)) if q.in_seconds().is_some() => {
+                let seconds = q.in_seconds().unwrap().round() as i64;
+                Ok(Value::Date(Date {
+                    seconds: match sign {
+                        '+' => self.seconds.checked_add(seconds),
+                        _ => self.seconds.checked_sub(seconds),
+                    }
+                    .ok_or_else(|| SyaError::NumberOverflow(q.to_string()))?,
+                    ..self
+                }))
+            }
+            ('+', Value::Date(_)) => Err(SyaError::InvalidOperation(sign)),
+            ('+' | '-', other) => Err(SyaError::ExpectedDuration(other.to_string())),
+            _ => Err(SyaError::InvalidOperation(sign)),
+        }
+    }
+
+    /// ISO day of the week, from 1 for Monday to 7 for Sunday.
+    pub fn weekday(&self) -> i64 {
+        let days = (self.seconds + self.offset.unwrap_or(0)).div_euclid(86400);
+        // 1970-01-01 was a Thursday.
+        (days + 3).rem_euclid(7) + 1
+    }
+}
+
+/// Calls the date function `name`, or returns `None` when `name` isn't one.
+pub fn call(name: &str, args: &[Value]) -> Option<Result<Value, SyaError>> {
+    let result = match (name, args) {
+        ("weekday", [Value::Date(d)]) => Ok(Value::Number(Number::Integer(d.weekday()))),
+        ("weekday", [v]) => Err(SyaError::ExpectedDate(v.to_string())),
+        ("weekday", _) => Err(SyaError::InvalidArguments(name.to_string())),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Reads an ISO date like `2024-05-20` or `2024-05-20T10:00:30+02:00` from
+/// the start of `input`, with its length in chars. Returns `None` when
+/// `input` doesn't start with one, so it's read as numbers instead.
+pub fn read(input: &[char]) -> Option<Result<(Date, usize), SyaError>> {
+    let digits = |at: usize, n: usize| -> Option<i64> {
+        let part = input.get(at..at + n)?;
+        match part.iter().all(char::is_ascii_digit) {
+            true => part.iter().collect::<String>().parse().ok(),
+            false => None,
+        }
+    };
+    let (year, month, day) = (digits(0, 4)?, digits(5, 2)?, digits(8, 2)?);
+    if input[4] != '-' || input[7] != '-' || input.get(10).is_some_and(char::is_ascii_digit) {
+        return None;
+    }
+    let mut end = 10;
+    let mut time = None;
+    let mut offset = None;
+    if input.get(10) == Some(&'T') {
+        let (hour, minute) = match (digits(11, 2), input.get(13), digits(14, 2)) {
+            (Some(h), Some(':'), Some(m)) => (h, m),
+            _ => return Some(Err(invalid(input, 11))),
+        };
+        end = 16;
+        let mut second = 0;
+        if input.get(16) == Some(&':') {
+            second = match digits(17, 2) {
+                Some(s) => s,
+                None => return Some(Err(invalid(input, 17))),
+            };
+            end = 19;
+        }
+        time = Some((hour, minute, second));
+        match input.get(end) {
+            Some('Z') => {
+                offset = Some(0);
+                end += 1;
+            }
+            Some(sign @ ('+' | '-')) if input.get(end + 3) == Some(&':') => {
+                if let (Some(h), Some(m)) = (digits(end + 1, 2), digits(end + 4, 2)) {
+                    let o = h * 3600 + m * 60;
+                    offset = Some(if *sign == '-' { -o } else { o });
+                    end += 6;
+                }
+            }
+            _ => {}
+        }
+    }
+    let text: String = input[..end].iter().collect();
+    let (hour, minute, second) = time.unwrap_or((0, 0, 0));
+    let valid = (1..=12).contains(&month)
+        && (1..=days_in_month(year, month)).contains(&day)
+        && hour < 24
+        && minute < 60
+        && second < 60
+        && offset.is_none_or(|o: i64| o.abs() < 24 * 3600);
+    if !valid {
+        return Some(Err(SyaError::InvalidDate(text)));
+    }
+    let local = days(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    let date = Date {
+        seconds: local - offset.unwrap_or(0),
+        offset,
+        time: time.is_some(),
+    };
+    Some(Ok((date, end)))
+}
+
+fn invalid(input: &[char], end: usize) -> SyaError {
+    SyaError::InvalidDate(input[..end.min(input.len())].iter().collect())
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The year, month and day `days` after 1970-01-01.
+fn civil(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
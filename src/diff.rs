@@ -0,0 +1,180 @@
+use crate::{
+    errors::SyaError,
+    expr::Expr,
+    number::{Angle, Number},
+    simplify::{simplify, SimplifyOptions},
+};
+
+/// Differentiates `expr` with respect to `var` and simplifies the result.
+/// Trigonometric functions take and return angles in `angle` units.
+pub fn diff(expr: &Expr, var: &str, angle: Angle) -> Result<Expr, SyaError> {
+    // Derivatives are full of `0 * x` and `x ^ 0` terms, the NaN caveats of
+    // the aggressive identities don't matter for a symbolic result.
+    let options = SimplifyOptions {
+        aggressive: true,
+        ..Default::default()
+    };
+    Ok(simplify(Derive { var, angle }.derive(expr)?, &options))
+}
+
+struct Derive<'a> {
+    var: &'a str,
+    angle: Angle,
+}
+impl Derive<'_> {
+    fn derive(&self, expr: &Expr) -> Result<Expr, SyaError> {
+        if !expr.contains(self.var) {
+            return Ok(int(0));
+        }
+
+        match expr {
+            Expr::Number(_) | Expr::Date(_) => Ok(int(0)),
+            Expr::Var(_) => Ok(int(1)),
+            Expr::Unary(s, e) => Ok(Expr::Unary(*s, Box::new(self.derive(e)?))),
+            Expr::Quantity(e, unit) => Ok(Expr::Quantity(Box::new(self.derive(e)?), unit.clone())),
+            Expr::Convert(e, unit) => Ok(Expr::Convert(Box::new(self.derive(e)?), unit.clone())),
+            Expr::Binary(sign, a, b) => {
+                let (a, b) = (a.as_ref().clone(), b.as_ref().clone());
+                match sign {
+                    '+' | '-' => Ok(bin(*sign, self.derive(&a)?, self.derive(&b)?)),
+                    '*' => Ok(bin(
+                        '+',
+                        bin('*', self.derive(&a)?, b.clone()),
+                        bin('*', a.clone(), self.derive(&b)?),
+                    )),
+                    '/' => Ok(bin(
+                        '/',
+                        bin(
+                            '-',
+                            bin('*', self.derive(&a)?, b.clone()),
+                            bin('*', a.clone(), self.derive(&b)?),
+                        ),
+                        bin('^', b, int(2)),
+                    )),
+                    // Power rule, the chain rule is the `* a'` term.
+                    '^' if !b.contains(self.var) => Ok(bin(
+                        '*',
+                        bin('*', b.clone(), bin('^', a.clone(), bin('-', b, int(1)))),
+                        self.derive(&a)?,
+                    )),
+                    // Exponential, a ^ b * ln(a) * b'
+                    '^' if !a.contains(self.var) => {
+                        Ok(bin('*', bin('*', expr.clone(), ln(a)), self.derive(&b)?))
+                    }
+                    // a ^ b * (b' * ln(a) + b * a' / a)
+                    '^' => Ok(bin(
+                        '*',
+                        expr.clone(),
+                        bin(
+                            '+',
+                            bin('*', self.derive(&b)?, ln(a.clone())),
+                            bin('/', bin('*', b, self.derive(&a)?), a),
+                        ),
+                    )),
+                    _ => Err(SyaError::NotDifferentiable(expr.to_string())),
+                }
+            }
+            Expr::Call(name, args) => match (name.as_str(), args.as_slice()) {
+                // (x y' - y x') / (x ^ 2 + y ^ 2)
+                ("atan2", [y, x]) => Ok(self.per_unit(bin(
+                    '/',
+                    bin(
+                        '-',
+                        bin('*', x.clone(), self.derive(y)?),
+                        bin('*', y.clone(), self.derive(x)?),
+                    ),
+                    bin(
+                        '+',
+                        bin('^', x.clone(), int(2)),
+                        bin('^', y.clone(), int(2)),
+                    ),
+                ))),
+                (_, [u]) => match self.outer(name, u) {
+                    Some(outer) => Ok(bin('*', outer, self.derive(u)?)),
+                    None => Err(SyaError::NotDifferentiable(expr.to_string())),
+                },
+                _ => Err(SyaError::NotDifferentiable(expr.to_string())),
+            },
+        }
+    }
+
+    /// Derivative of the function `name` at `u`, the chain rule multiplies it
+    /// by `u'`.
+    fn outer(&self, name: &str, u: &Expr) -> Option<Expr> {
+        let f = |name: &str| call(name, u.clone());
+        let squared = |e: Expr| bin('^', e, int(2));
+        // 1 / sqrt(1 - u ^ 2)
+        let arc = || {
+            bin(
+                '/',
+                int(1),
+                call("sqrt", bin('-', int(1), squared(u.clone()))),
+            )
+        };
+        let derivative = match name {
+            "sin" => self.in_units(f("cos")),
+            "cos" => self.in_units(neg(f("sin"))),
+            "tan" => self.in_units(bin('/', int(1), squared(f("cos")))),
+            "asin" => self.per_unit(arc()),
+            "acos" => self.per_unit(neg(arc())),
+            "atan" => self.per_unit(bin('/', int(1), bin('+', int(1), squared(u.clone())))),
+            "sinh" => f("cosh"),
+            "cosh" => f("sinh"),
+            "tanh" => bin('/', int(1), squared(f("cosh"))),
+            "exp" => f("exp"),
+            "ln" => bin('/', int(1), u.clone()),
+            "log10" | "log2" => {
+                let base = if name == "log10" { 10 } else { 2 };
+                bin('/', int(1), bin('*', u.clone(), call("ln", int(base))))
+            }
+            "sqrt" => bin('/', int(1), bin('*', int(2), f("sqrt"))),
+            "cbrt" => bin('/', int(1), bin('*', int(3), squared(f("cbrt")))),
+            "abs" => f("sign"),
+            "sign" => int(0),
+            _ => return None,
+        };
+        Some(derivative)
+    }
+
+    /// Scales the derivative of a function of an angle, in degrees
+    /// `sin(x)'` is `cos(x) * π / 180`.
+    fn in_units(&self, e: Expr) -> Expr {
+        match self.angle {
+            Angle::Rad => e,
+            angle => bin('*', e, Expr::Number(Number::Float(angle.radians()))),
+        }
+    }
+
+    /// Scales the derivative of a function returning an angle, the inverse of
+    /// `in_units`.
+    fn per_unit(&self, e: Expr) -> Expr {
+        match self.angle {
+            Angle::Rad => e,
+            angle => bin('/', e, Expr::Number(Number::Float(angle.radians()))),
+        }
+    }
+}
+
+fn bin(sign: char, a: Expr, b: Expr) -> Expr {
+    Expr::Binary(sign, Box::new(a), Box::new(b))
+}
+
+fn neg(e: Expr) -> Expr {
+    Expr::Unary('-', Box::new(e))
+}
+
+fn call(name: &str, arg: Expr) -> Expr {
+    Expr::Call(name.to_string(), vec![arg])
+}
+
+/// The natural logarithm, 1 for `e` itself.
+fn ln(e: Expr) -> Expr {
+    match e {
+        Expr::Var(ref name) if name == "e" => int(1),
+        e => call("ln", e),
+    }
+}
+
+fn int(i: i64) -> Expr {
+    Expr::Number(Number::Integer(i))
+}
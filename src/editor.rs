@@ -0,0 +1,458 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, IsTerminal, Read, Write},
+    path::PathBuf,
+};
+
+use crate::config::config_dir;
+
+const HISTORY_LIMIT: usize = 1000;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Esc,
+}
+
+/// Reads one key press, decoding UTF-8 characters and ANSI escape sequences.
+pub fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    let byte = match read_byte(input)? {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        127 | 8 => Key::Backspace,
+        27 => read_escape(input)?,
+        1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+        0..=31 => Key::Esc,
+        _ => {
+            let len = match byte {
+                0xF0..=0xFF => 4,
+                0xE0..=0xEF => 3,
+                0xC0..=0xDF => 2,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..len {
+                bytes.extend(read_byte(input)?);
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Esc,
+            }
+        }
+    };
+    Ok(Some(key))
+}
+
+fn read_escape<R: Read>(input: &mut R) -> io::Result<Key> {
+    let kind = match read_byte(input)? {
+        Some(b @ (b'[' | b'O')) => b,
+        _ => return Ok(Key::Esc),
+    };
+
+    let mut param = String::new();
+    loop {
+        match read_byte(input)? {
+            Some(b) if b.is_ascii_digit() || b == b';' => param.push(b as char),
+            Some(b) => {
+                return Ok(match (kind, b, param.as_str()) {
+                    (_, b'A', _) => Key::Up,
+                    (_, b'B', _) => Key::Down,
+                    (_, b'C', _) => Key::Right,
+                    (_, b'D', _) => Key::Left,
+                    (_, b'H', _) | (b'[', b'~', "1" | "7") => Key::Home,
+                    (_, b'F', _) | (b'[', b'~', "4" | "8") => Key::End,
+                    (b'[', b'~', "3") => Key::Delete,
+                    _ => Key::Esc,
+                })
+            }
+            None => return Ok(Key::Esc),
+        }
+    }
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut buf = [0u8; 1];
+    match input.read(&mut buf)? {
+        0 => Ok(None),
+        _ => Ok(Some(buf[0])),
+    }
+}
+
+/// Styles a line given the cursor position, or `None` once the line is done.
+pub type Highlight = Box<dyn Fn(&str, Option<usize>) -> String>;
+
+/// A line editor with history, Emacs style bindings and reverse
+/// incremental search (Ctrl-R). Falls back to plain line reading when stdin
+/// isn't a terminal. The default editor has an empty history that isn't
+/// saved.
+#[derive(Default)]
+pub struct Editor {
+    pub history: Vec<String>,
+    history_file: Option<PathBuf>,
+    /// Styles the line while editing.
+    pub highlight: Option<Highlight>,
+    /// Words completed with Tab, with the description listed when several
+    /// match.
+    pub completions: Vec<(String, String)>,
+    /// Whether the terminal refused raw mode, which is only reported once.
+    raw_failed: bool,
+}
+impl Editor {
+    pub fn new() -> Editor {
+        let history_file = config_dir().map(|d| d.join("history"));
+        let history = history_file
+            .as_ref()
+            .and_then(|f| fs::read_to_string(f).ok())
+            .map(|h| h.lines().map(str::to_string).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let skip = history.len().saturating_sub(HISTORY_LIMIT);
+
+        Editor {
+            history: history.into_iter().skip(skip).collect(),
+            history_file,
+            highlight: None,
+            completions: Vec::new(),
+            raw_failed: false,
+        }
+    }
+
+    /// Returns `None` on end of input.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let raw = match io::stdin().is_terminal() && io::stdout().is_terminal() {
+            true => RawMode::enable(),
+            false => None,
+        };
+        if raw.is_none() && io::stdin().is_terminal() && !self.raw_failed {
+            self.raw_failed = true;
+            eprintln!("Can't put the terminal in raw mode, line editing is off");
+        }
+
+        let line = match raw {
+            Some(_) => self.edit(prompt, &mut io::stdin().lock(), &mut io::stdout())?,
+            None => {
+                print!("{}", prompt);
+                io::stdout().flush()?;
+                let mut line = String::new();
+                match io::stdin().read_line(&mut line)? {
+                    0 => None,
+                    _ => Some(line.trim_end_matches(['\r', '\n']).to_string()),
+                }
+            }
+        };
+
+        if let Some(line) = &line {
+            self.add_history(line);
+        }
+        Ok(line)
+    }
+
+    fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+
+        // History is best effort, failing to save it shouldn't stop the prompt.
+        if let Some(file) = &self.history_file {
+            if let Some(dir) = file.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(file) {
+                let _ = writeln!(f, "{}", line);
+            }
+        }
+    }
+
+    /// Edits a line reading keys from `input` and drawing on `out`.
+    pub fn edit<R: Read, W: Write>(
+        &self,
+        prompt: &str,
+        input: &mut R,
+        out: &mut W,
+    ) -> io::Result<Option<String>> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // Position while browsing history, `history.len()` is the new line.
+        let mut index = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+        let mut search: Option<String> = None;
+
+        self.render(out, prompt, &line, Some(cursor))?;
+        while let Some(key) = read_key(input)? {
+            if let Some(query) = search.as_mut() {
+                match key {
+                    Key::Char(c) => query.push(c),
+                    Key::Backspace => {
+                        query.pop();
+                    }
+                    // Ctrl-R again looks for an older match.
+                    Key::Ctrl('r') => index = index.saturating_sub(1),
+                    Key::Ctrl('g') | Key::Esc => {
+                        search = None;
+                        self.render(out, prompt, &line, Some(cursor))?;
+                        continue;
+                    }
+                    _ => {
+                        search = None;
+                        if key == Key::Enter {
+                            self.render(out, prompt, &line, None)?;
+                            write!(out, "\r\n")?;
+                            return Ok(Some(line.iter().collect()));
+                        }
+                        self.render(out, prompt, &line, Some(cursor))?;
+                        continue;
+                    }
+                }
+                let query = query.clone();
+                if let Some(i) = self.search(&query, index) {
+                    index = i;
+                    line = self.history[i].chars().collect();
+                    cursor = line.len();
+                }
+                let found: String = line.iter().collect();
+                write!(out, "\r(reverse-i-search)'{}': {}\x1b[K", query, found)?;
+                out.flush()?;
+                continue;
+            }
+
+            match key {
+                Key::Enter => {
+                    self.render(out, prompt, &line, None)?;
+                    write!(out, "\r\n")?;
+                    return Ok(Some(line.iter().collect()));
+                }
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Ctrl('d') if line.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                Key::Ctrl('d') if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Ctrl('c') => {
+                    write!(out, "^C\r\n")?;
+                    return Ok(Some(String::new()));
+                }
+                Key::Left | Key::Ctrl('b') => cursor = cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => cursor = (cursor + 1).min(line.len()),
+                Key::Home | Key::Ctrl('a') => cursor = 0,
+                Key::End | Key::Ctrl('e') => cursor = line.len(),
+                Key::Ctrl('k') => line.truncate(cursor),
+                Key::Ctrl('u') => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::Ctrl('w') => {
+                    let mut start = cursor;
+                    while start > 0 && line[start - 1] == ' ' {
+                        start -= 1;
+                    }
+                    while start > 0 && line[start - 1] != ' ' {
+                        start -= 1;
+                    }
+                    line.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Ctrl('l') => write!(out, "\x1b[2J\x1b[H")?,
+                Key::Ctrl('i') => self.complete(out, &mut line, &mut cursor)?,
+                Key::Up | Key::Ctrl('p') if index > 0 => {
+                    if index == self.history.len() {
+                        draft = line.clone();
+                    }
+                    index -= 1;
+                    line = self.history[index].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down | Key::Ctrl('n') if index < self.history.len() => {
+                    index += 1;
+                    line = match self.history.get(index) {
+                        Some(h) => h.chars().collect(),
+                        None => draft.clone(),
+                    };
+                    cursor = line.len();
+                }
+                Key::Ctrl('r') => {
+                    search = Some(String::new());
+                    index = self.history.len();
+                    write!(out, "\r(reverse-i-search)'': \x1b[K")?;
+                    out.flush()?;
+                    continue;
+                }
+                _ => {}
+            }
+            self.render(out, prompt, &line, Some(cursor))?;
+        }
+        Ok(None)
+    }
+
+    /// Completes the word before the cursor up to where the matching
+    /// completions differ, listing them when that adds nothing.
+    fn complete<W: Write>(
+        &self,
+        out: &mut W,
+        line: &mut Vec<char>,
+        cursor: &mut usize,
+    ) -> io::Result<()> {
+        let mut start = *cursor;
+        while start > 0 && (line[start - 1].is_ascii_alphanumeric() || line[start - 1] == '_') {
+            start -= 1;
+        }
+        if start > 0 && matches!(line[start - 1], ':' | '$') {
+            start -= 1;
+        }
+        let word: String = line[start..*cursor].iter().collect();
+        let candidates: Vec<&(String, String)> = self
+            .completions
+            .iter()
+            .filter(|(c, _)| c.starts_with(&word) && (word.starts_with(':') || !c.starts_with(':')))
+            .collect();
+
+        let common = match candidates.split_first() {
+            Some((first, rest)) => rest.iter().fold(first.0.as_str(), |common, (c, _)| {
+                let len = common
+                    .char_indices()
+                    .zip(c.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(common.len().min(c.len()), |((i, _), _)| i);
+                &common[..len]
+            }),
+            None => return write!(out, "\x07"),
+        };
+        if common.len() > word.len() || candidates.len() == 1 {
+            for c in common[word.len()..].chars() {
+                line.insert(*cursor, c);
+                *cursor += 1;
+            }
+            return Ok(());
+        }
+
+        let descriptions: Vec<&str> = candidates.iter().map(|(_, d)| d.as_str()).collect();
+        write!(out, "\r\n{}\r\n", descriptions.join("  "))
+    }
+
+    /// Draws the line with the cursor at `cursor`, or at the end when `None`.
+    fn render<W: Write>(
+        &self,
+        out: &mut W,
+        prompt: &str,
+        line: &[char],
+        cursor: Option<usize>,
+    ) -> io::Result<()> {
+        let text: String = line.iter().collect();
+        let text = match &self.highlight {
+            Some(highlight) => highlight(&text, cursor),
+            None => text,
+        };
+        write!(out, "\r{}{}\x1b[K\r", prompt, text)?;
+        let column = prompt.chars().count() + cursor.unwrap_or(line.len());
+        if column > 0 {
+            write!(out, "\x1b[{}C", column)?;
+        }
+        out.flush()
+    }
+
+    /// Finds the newest history entry containing `query`, at or before `from`.
+    fn search(&self, query: &str, from: usize) -> Option<usize> {
+        let end = from.min(self.history.len().saturating_sub(1));
+        (0..=end)
+            .rev()
+            .find(|&i| self.history.get(i).is_some_and(|h| h.contains(query)))
+    }
+}
+
+/// Puts the terminal in raw mode with termios, restoring the previous
+/// settings when dropped.
+struct RawMode {
+    #[cfg(unix)]
+    saved: termios::Termios,
+}
+impl RawMode {
+    #[cfg(unix)]
+    fn enable() -> Option<RawMode> {
+        let saved = termios::get()?;
+        let mut raw = saved;
+        termios::make_raw(&mut raw);
+        termios::set(&raw)?;
+        Some(RawMode { saved })
+    }
+
+    #[cfg(not(unix))]
+    fn enable() -> Option<RawMode> {
+        None
+    }
+}
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        termios::set(&self.saved);
+    }
+}
+
+/// The termios calls of libc for standard input, which std doesn't expose.
+#[cfg(unix)]
+mod termios {
+    /// `struct termios`, kept opaque so the layout of each platform doesn't
+    /// matter. It's larger and more aligned than any of them.
+    #[derive(Clone, Copy)]
+    #[repr(C, align(8))]
+    pub struct Termios([u8; 256]);
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, action: i32, termios: *const Termios) -> i32;
+        fn cfmakeraw(termios: *mut Termios);
+    }
+
+    /// Applies the settings right away, the same on every Unix.
+    const TCSANOW: i32 = 0;
+
+    pub fn get() -> Option<Termios> {
+        let mut termios = Termios([0; 256]);
+        // SAFETY: the buffer is large enough for `struct termios`.
+        match unsafe { tcgetattr(0, &mut termios) } {
+            0 => Some(termios),
+            _ => None,
+        }
+    }
+
+    pub fn set(termios: &Termios) -> Option<()> {
+        // SAFETY: `termios` was filled in by `tcgetattr`.
+        match unsafe { tcsetattr(0, TCSANOW, termios) } {
+            0 => Some(()),
+            _ => None,
+        }
+    }
+
+    /// Turns off echo, line buffering, signals and output processing.
+    pub fn make_raw(termios: &mut Termios) {
+        // SAFETY: `termios` was filled in by `tcgetattr`.
+        unsafe { cfmakeraw(termios) }
+    }
+}
@@ -11,11 +11,99 @@ pub enum SyaError {
     WrongUnary(char),
     InvalidOperation(char),
     NumberOverflow(String),
+    NotInteger(String),
     Custom(String),
     DivisionByZero,
     ExpectedStackSize(u32),
+    UnknownVariable(String),
+    UnknownResult(String),
+    ReadOnly(String),
+    UnknownFunction(String),
+    InvalidArguments(String),
+    NotDifferentiable(String),
+    NoConvergence(usize),
+    NotBracketed(f64, f64),
+    RecursionLimit(String),
+    OutOfDomain(String, String),
+    Pole(String, String),
+    LengthMismatch(usize, usize),
+    IndexOutOfRange(i64, usize),
+    ExpectedList(String),
+    ExpectedNumber(String),
+    ExpectedMatrix(String),
+    DimensionMismatch((usize, usize), (usize, usize)),
+    NotSquare(usize, usize),
+    Singular,
+    MatrixTooLarge(i64),
+    UnknownUnit(String),
+    IncompatibleUnits(String, String),
+    UnitPower(String),
+    InvalidDate(String),
+    ExpectedDate(String),
+    ExpectedDuration(String),
+    Statement(usize, Box<SyaError>),
 }
 impl Error for SyaError {}
+impl SyaError {
+    /// Whether the input is malformed, as opposed to failing to evaluate.
+    pub fn is_syntax(&self) -> bool {
+        match self {
+            SyaError::InvalidToken(_)
+            | SyaError::InvalidChar(_)
+            | SyaError::InvalidInput
+            | SyaError::ExpectedChar(_)
+            | SyaError::InvalidDate(_)
+            | SyaError::ExpectedStackSize(_) => true,
+            SyaError::Statement(_, e) => e.is_syntax(),
+            _ => false,
+        }
+    }
+
+    /// Name of the variant, for machine readable output. Statements report
+    /// the kind of the error inside them.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SyaError::InvalidToken(_) => "InvalidToken",
+            SyaError::InvalidChar(_) => "InvalidChar",
+            SyaError::InvalidInput => "InvalidInput",
+            SyaError::ExpectedChar(_) => "ExpectedChar",
+            SyaError::WrongUnary(_) => "WrongUnary",
+            SyaError::InvalidOperation(_) => "InvalidOperation",
+            SyaError::NumberOverflow(_) => "NumberOverflow",
+            SyaError::NotInteger(_) => "NotInteger",
+            SyaError::Custom(_) => "Custom",
+            SyaError::DivisionByZero => "DivisionByZero",
+            SyaError::ExpectedStackSize(_) => "ExpectedStackSize",
+            SyaError::UnknownVariable(_) => "UnknownVariable",
+            SyaError::UnknownResult(_) => "UnknownResult",
+            SyaError::ReadOnly(_) => "ReadOnly",
+            SyaError::UnknownFunction(_) => "UnknownFunction",
+            SyaError::InvalidArguments(_) => "InvalidArguments",
+            SyaError::NotDifferentiable(_) => "NotDifferentiable",
+            SyaError::NoConvergence(_) => "NoConvergence",
+            SyaError::NotBracketed(..) => "NotBracketed",
+            SyaError::RecursionLimit(_) => "RecursionLimit",
+            SyaError::OutOfDomain(..) => "OutOfDomain",
+            SyaError::Pole(..) => "Pole",
+            SyaError::LengthMismatch(..) => "LengthMismatch",
+            SyaError::IndexOutOfRange(..) => "IndexOutOfRange",
+            SyaError::ExpectedList(_) => "ExpectedList",
+            SyaError::ExpectedNumber(_) => "ExpectedNumber",
+            SyaError::ExpectedMatrix(_) => "ExpectedMatrix",
+            SyaError::DimensionMismatch(..) => "DimensionMismatch",
+            SyaError::NotSquare(..) => "NotSquare",
+            SyaError::Singular => "Singular",
+            SyaError::MatrixTooLarge(_) => "MatrixTooLarge",
+            SyaError::UnknownUnit(_) => "UnknownUnit",
+            SyaError::UnitPower(_) => "UnitPower",
+            SyaError::IncompatibleUnits(..) => "IncompatibleUnits",
+            SyaError::InvalidDate(_) => "InvalidDate",
+            SyaError::ExpectedDate(_) => "ExpectedDate",
+            SyaError::ExpectedDuration(_) => "ExpectedDuration",
+            SyaError::Statement(_, e) => e.kind(),
+        }
+    }
+}
 impl Display for SyaError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -25,11 +113,56 @@ impl Display for SyaError {
             SyaError::ExpectedChar(c) => write!(f, "Expected Character '{}'", c),
             SyaError::InvalidOperation(c) => write!(f, "Invalid Operation '{}'", c),
             SyaError::NumberOverflow(i) => write!(f, "Number Overflow '{}'", i),
+            SyaError::NotInteger(n) => write!(f, "Expected an integer, got '{}'", n),
             SyaError::InvalidInput => write!(f, "Invalid Input"),
             SyaError::ExpectedStackSize(u) => {
                 write!(f, "Expected Stack to have at least {} items", u)
             }
             SyaError::DivisionByZero => write!(f, "Tried to divide by zero"),
+            SyaError::UnknownVariable(v) => write!(f, "Unknown Variable '{}'", v),
+            SyaError::UnknownResult(r) => write!(f, "No result '{}' yet", r),
+            SyaError::ReadOnly(v) => write!(f, "Can't assign to '{}'", v),
+            SyaError::UnknownFunction(n) => write!(f, "Unknown Function '{}'", n),
+            SyaError::InvalidArguments(n) => write!(f, "Invalid Arguments for '{}'", n),
+            SyaError::NotDifferentiable(e) => write!(f, "Can't differentiate '{}'", e),
+            SyaError::NoConvergence(n) => write!(f, "Didn't converge after {} iterations", n),
+            SyaError::NotBracketed(a, b) => write!(f, "No sign change between {} and {}", a, b),
+            SyaError::RecursionLimit(n) => write!(f, "Too many nested calls of '{}'", n),
+            SyaError::OutOfDomain(n, x) => write!(f, "'{}' is undefined for {}", n, x),
+            SyaError::Pole(n, x) => write!(f, "'{}' is infinite at {}", n, x),
+            SyaError::LengthMismatch(a, b) => {
+                write!(f, "Lists have different lengths, {} and {}", a, b)
+            }
+            SyaError::IndexOutOfRange(i, len) => {
+                write!(f, "Index {} is out of range for a list of {}", i, len)
+            }
+            SyaError::ExpectedList(v) => write!(f, "Expected a list, got '{}'", v),
+            SyaError::ExpectedNumber(v) => write!(f, "Expected a number, got '{}'", v),
+            SyaError::ExpectedMatrix(v) => write!(f, "Expected a matrix, got '{}'", v),
+            SyaError::DimensionMismatch((r1, c1), (r2, c2)) => write!(
+                f,
+                "Matrix dimensions don't match, {}x{} and {}x{}",
+                r1, c1, r2, c2
+            ),
+            SyaError::NotSquare(r, c) => write!(f, "Expected a square matrix, got {}x{}", r, c),
+            SyaError::Singular => write!(f, "Matrix is singular"),
+            SyaError::MatrixTooLarge(n) => write!(
+                f,
+                "Matrices are limited to {} rows, got {}",
+                crate::matrix::MAX_SIZE,
+                n
+            ),
+            SyaError::UnknownUnit(u) => write!(f, "Unknown unit '{}'", u),
+            SyaError::UnitPower(p) => {
+                write!(f, "Units can only be raised to integer powers, got '{}'", p)
+            }
+            SyaError::IncompatibleUnits(a, b) => {
+                write!(f, "Units '{}' and '{}' are incompatible", a, b)
+            }
+            SyaError::InvalidDate(d) => write!(f, "Invalid date '{}'", d),
+            SyaError::ExpectedDate(v) => write!(f, "Expected a date, got '{}'", v),
+            SyaError::ExpectedDuration(v) => write!(f, "Expected a duration, got '{}'", v),
+            SyaError::Statement(n, e) => write!(f, "Statement {}: {}", n, e),
             SyaError::Custom(s) => write!(f, "{}", s),
         }
     }
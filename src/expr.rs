@@ -0,0 +1,281 @@
+use core::fmt;
+
+use crate::{
+    dates::Date,
+    errors::SyaError,
+    number::Number,
+    tokenizer::{Operator, Precedence, Token},
+    units::Unit,
+    value::list,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Number(Number),
+    Var(String),
+    Unary(char, Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// A value with a unit, like `3 m`.
+    Quantity(Box<Expr>, Unit),
+    /// A value converted to a unit, like `x to km`.
+    Convert(Box<Expr>, Unit),
+    Date(Date),
+}
+impl Expr {
+    pub fn from_rpn(rpn: &[Token]) -> Result<Expr, SyaError> {
+        let mut stack = Expr::from_rpn_many(rpn)?;
+        if stack.len() != 1 {
+            return Err(SyaError::InvalidInput);
+        }
+        Ok(stack.pop().unwrap())
+    }
+
+    /// Builds every expression left on the stack, like the arguments of a
+    /// function call.
+    pub fn from_rpn_many(rpn: &[Token]) -> Result<Vec<Expr>, SyaError> {
+        let mut stack = Vec::new();
+        for token in rpn {
+            match token {
+                Token::Number(n) => stack.push(Expr::Number(n.clone())),
+                Token::Date(d) => stack.push(Expr::Date(d.clone())),
+                Token::Ident(name) => stack.push(Expr::Var(name.clone())),
+                Token::UNARY(s) => {
+                    let e = stack.pop().ok_or(SyaError::ExpectedStackSize(1))?;
+                    stack.push(Expr::Unary(*s, Box::new(e)));
+                }
+                Token::Operator(_) | Token::ASSIGN => {
+                    if stack.len() < 2 {
+                        return Err(SyaError::ExpectedStackSize(2));
+                    }
+                    let b = stack.pop().unwrap();
+                    let a = stack.pop().unwrap();
+                    let sign = match token {
+                        Token::Operator(o) => o.sign,
+                        _ => '=',
+                    };
+                    stack.push(Expr::Binary(sign, Box::new(a), Box::new(b)));
+                }
+                Token::Call(name, argc) => {
+                    if stack.len() < *argc {
+                        return Err(SyaError::ExpectedStackSize(*argc as u32));
+                    }
+                    let args = stack.split_off(stack.len() - argc);
+                    stack.push(Expr::Call(name.clone(), args));
+                }
+                Token::Unit(unit) | Token::Convert(unit) => {
+                    let e = Box::new(stack.pop().ok_or(SyaError::ExpectedStackSize(1))?);
+                    stack.push(match token {
+                        Token::Unit(_) => Expr::Quantity(e, unit.clone()),
+                        _ => Expr::Convert(e, unit.clone()),
+                    });
+                }
+                _ => return Err(SyaError::InvalidToken(token.clone())),
+            }
+        }
+        Ok(stack)
+    }
+
+    /// Returns whether `var` appears anywhere in the expression.
+    pub fn contains(&self, var: &str) -> bool {
+        match self {
+            Expr::Number(_) | Expr::Date(_) => false,
+            Expr::Var(name) => name == var,
+            Expr::Unary(_, e) | Expr::Quantity(e, _) | Expr::Convert(e, _) => e.contains(var),
+            Expr::Binary(_, a, b) => a.contains(var) || b.contains(var),
+            Expr::Call(_, args) => args.iter().any(|a| a.contains(var)),
+        }
+    }
+
+    /// Returns every variable in the expression, in order of appearance.
+    pub fn vars(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        self.push_vars(&mut vars);
+        vars
+    }
+
+    fn push_vars(&self, vars: &mut Vec<String>) {
+        match self {
+            Expr::Number(_) | Expr::Date(_) => {}
+            Expr::Var(name) => {
+                if !vars.contains(name) {
+                    vars.push(name.clone());
+                }
+            }
+            Expr::Unary(_, e) | Expr::Quantity(e, _) | Expr::Convert(e, _) => e.push_vars(vars),
+            Expr::Binary(_, a, b) => {
+                a.push_vars(vars);
+                b.push_vars(vars);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|a| a.push_vars(vars)),
+        }
+    }
+
+    /// Replaces every occurrence of `var` with `with`.
+    pub fn replace(&self, var: &str, with: &Expr) -> Expr {
+        match self {
+            Expr::Var(name) if name == var => with.clone(),
+            Expr::Unary(s, e) => Expr::Unary(*s, Box::new(e.replace(var, with))),
+            Expr::Quantity(e, u) => Expr::Quantity(Box::new(e.replace(var, with)), u.clone()),
+            Expr::Convert(e, u) => Expr::Convert(Box::new(e.replace(var, with)), u.clone()),
+            Expr::Binary(s, a, b) => Expr::Binary(
+                *s,
+                Box::new(a.replace(var, with)),
+                Box::new(b.replace(var, with)),
+            ),
+            Expr::Call(name, args) => Expr::Call(
+                name.clone(),
+                args.iter().map(|a| a.replace(var, with)).collect(),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// Replaces every variable in `vars` at once, so replacements aren't
+    /// replaced again.
+    pub fn replace_all(&self, vars: &[(String, Expr)]) -> Expr {
+        match self {
+            Expr::Var(name) => match vars.iter().find(|(v, _)| v == name) {
+                Some((_, with)) => with.clone(),
+                None => self.clone(),
+            },
+            Expr::Unary(s, e) => Expr::Unary(*s, Box::new(e.replace_all(vars))),
+            Expr::Quantity(e, u) => Expr::Quantity(Box::new(e.replace_all(vars)), u.clone()),
+            Expr::Convert(e, u) => Expr::Convert(Box::new(e.replace_all(vars)), u.clone()),
+            Expr::Binary(s, a, b) => Expr::Binary(
+                *s,
+                Box::new(a.replace_all(vars)),
+                Box::new(b.replace_all(vars)),
+            ),
+            Expr::Call(name, args) => Expr::Call(
+                name.clone(),
+                args.iter().map(|a| a.replace_all(vars)).collect(),
+            ),
+            Expr::Number(_) | Expr::Date(_) => self.clone(),
+        }
+    }
+
+    pub fn to_rpn(&self) -> Vec<Token> {
+        let mut out = Vec::new();
+        self.push_rpn(&mut out);
+        out
+    }
+
+    fn push_rpn(&self, out: &mut Vec<Token>) {
+        match self {
+            Expr::Number(n) => out.push(Token::Number(n.clone())),
+            Expr::Date(d) => out.push(Token::Date(d.clone())),
+            Expr::Var(name) => out.push(Token::Ident(name.clone())),
+            Expr::Unary(s, e) => {
+                e.push_rpn(out);
+                out.push(Token::UNARY(*s));
+            }
+            Expr::Binary(sign, a, b) => {
+                a.push_rpn(out);
+                b.push_rpn(out);
+                if *sign == '=' {
+                    out.push(Token::ASSIGN);
+                } else {
+                    out.push(Token::Operator(Operator {
+                        sign: *sign,
+                        precedence: precedence(*sign),
+                    }));
+                }
+            }
+            Expr::Call(name, args) => {
+                for a in args {
+                    a.push_rpn(out);
+                }
+                out.push(Token::Call(name.clone(), args.len()));
+            }
+            Expr::Quantity(e, unit) => {
+                e.push_rpn(out);
+                out.push(Token::Unit(unit.clone()));
+            }
+            Expr::Convert(e, unit) => {
+                e.push_rpn(out);
+                out.push(Token::Convert(unit.clone()));
+            }
+        }
+    }
+
+    fn precedence(&self) -> Precedence {
+        match self {
+            Expr::Binary(sign, _, _) => precedence(*sign),
+            Expr::Number(Number::Rational(..)) => Precedence::MUL,
+            Expr::Convert(..) => Precedence::MIN,
+            _ => Precedence::MAX,
+        }
+    }
+}
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Date(d) => write!(f, "{}", d),
+            Expr::Var(name) => write!(f, "{}", name),
+            Expr::Unary(s, e) => match **e {
+                Expr::Binary(..) => write!(f, "{}({})", s, e),
+                _ => write!(f, "{}{}", s, e),
+            },
+            Expr::Binary(sign, a, b) => {
+                // Every operator is left associative, so the right side needs
+                // parentheses on equal precedence as well.
+                let p = precedence(*sign);
+                if a.precedence() < p {
+                    write!(f, "({})", a)?;
+                } else {
+                    write!(f, "{}", a)?;
+                }
+                write!(f, " {} ", sign)?;
+                if b.precedence() <= p {
+                    write!(f, "({})", b)
+                } else {
+                    write!(f, "{}", b)
+                }
+            }
+            // A unit only follows a number or a parenthesis.
+            Expr::Quantity(e, unit) => match **e {
+                Expr::Number(ref n @ (Number::Integer(_) | Number::Float(_) | Number::Big(_)))
+                    if n.as_f64() >= 0.0 =>
+                {
+                    write!(f, "{} {}", n, unit)
+                }
+                _ => write!(f, "({}) {}", e, unit),
+            },
+            Expr::Convert(e, unit) => write!(f, "{} to {}", e, unit),
+            Expr::Call(name, args) if name == "list" => {
+                write!(f, "{}", list(args, |a| a.to_string()))
+            }
+            Expr::Call(name, args)
+                if matches!(name.as_str(), "index" | "slice") && !args.is_empty() =>
+            {
+                let (value, bounds) = args.split_first().unwrap();
+                match value {
+                    Expr::Unary(..) => write!(f, "({})", value)?,
+                    _ if value.precedence() == Precedence::MAX => write!(f, "{}", value)?,
+                    _ => write!(f, "({})", value)?,
+                }
+                let bounds: Vec<String> = bounds.iter().map(|b| b.to_string()).collect();
+                match (name.as_str(), bounds.as_slice()) {
+                    ("slice", [start]) => write!(f, "[{}:]", start),
+                    ("slice", _) => write!(f, "[{}]", bounds.join(":")),
+                    _ => write!(f, "[{}]", bounds.join(", ")),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+pub fn precedence(sign: char) -> Precedence {
+    match sign {
+        '+' | '-' => Precedence::SUM,
+        '*' | '/' => Precedence::MUL,
+        '^' => Precedence::EXP,
+        _ => Precedence::MIN,
+    }
+}
@@ -0,0 +1,173 @@
+use core::fmt;
+
+use crate::{
+    number::Number,
+    value::{list, Value},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Radix {
+    #[default]
+    Dec,
+    Hex,
+    Bin,
+}
+impl Radix {
+    pub fn parse(s: &str) -> Option<Radix> {
+        match s {
+            "dec" => Some(Radix::Dec),
+            "hex" => Some(Radix::Hex),
+            "bin" => Some(Radix::Bin),
+            _ => None,
+        }
+    }
+}
+
+/// How results are shown.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Format {
+    /// Digits shown after the decimal point, all when `None`.
+    pub precision: Option<usize>,
+    /// Separates thousands with `,`, and hex or binary digits in fours with
+    /// `_`.
+    pub group: bool,
+    /// Adds the type of the result, like `60 (integer)`.
+    pub types: bool,
+    /// Radix of integers, other numbers are always decimal.
+    pub radix: Radix,
+    /// Shows exact values as fractions rather than decimals.
+    pub fractions: bool,
+}
+impl Default for Format {
+    fn default() -> Self {
+        Format {
+            precision: None,
+            group: false,
+            types: false,
+            radix: Radix::Dec,
+            fractions: true,
+        }
+    }
+}
+
+impl Format {
+    pub fn value(&self, value: &Value) -> String {
+        match self.types {
+            true => format!("{} ({})", self.text(value), type_name(value)),
+            false => self.text(value),
+        }
+    }
+
+    fn text(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => self.number(n),
+            Value::Expr(e) => e.to_string(),
+            Value::List(items) => list(items, |v| self.text(v)),
+            Value::Quantity(q) => match q.parts() {
+                Some(parts) => parts
+                    .iter()
+                    .map(|(n, unit)| format!("{} {}", self.number(n), unit))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                None => format!("{} {}", self.number(&q.value), q.unit),
+            },
+            Value::Date(d) => d.to_string(),
+        }
+    }
+
+    fn number(&self, n: &Number) -> String {
+        match rounded(n.clone(), self.precision) {
+            Number::Integer(i) => self.integer(i),
+            Number::Rational(n, d) if self.fractions => {
+                format!("{}/{}", self.integer(n), self.integer(d))
+            }
+            Number::Rational(n, d) => self.float(n as f64 / d as f64),
+            Number::Float(f) => self.float(f),
+            Number::Big(b) => self.whole(b.is_negative(), b.abs()),
+        }
+    }
+
+    fn integer(&self, i: i64) -> String {
+        self.whole(i < 0, i.unsigned_abs())
+    }
+
+    /// An integer of any size, given its sign and magnitude.
+    fn whole<T: fmt::Display + fmt::LowerHex + fmt::Binary>(
+        &self,
+        negative: bool,
+        magnitude: T,
+    ) -> String {
+        let sign = if negative { "-" } else { "" };
+        let (prefix, digits, size, separator) = match self.radix {
+            Radix::Dec => ("", magnitude.to_string(), 3, ','),
+            Radix::Hex => ("0x", format!("{:x}", magnitude), 4, '_'),
+            Radix::Bin => ("0b", format!("{:b}", magnitude), 4, '_'),
+        };
+        let digits = match self.group {
+            true => group(&digits, size, separator),
+            false => digits,
+        };
+        format!("{}{}{}", sign, prefix, digits)
+    }
+
+    fn float(&self, f: f64) -> String {
+        let f = match self.precision {
+            Some(digits) => Number::Float(f).round_digits(digits).as_f64(),
+            None => f,
+        };
+        if f.is_finite()
+            && f.fract() == 0.0
+            && self.radix != Radix::Dec
+            && f.abs() < i64::MAX as f64
+        {
+            return self.integer(f as i64);
+        }
+        let text = f.to_string();
+        if !self.group || !f.is_finite() {
+            return text;
+        }
+        match text.split_once('.') {
+            Some((whole, fraction)) => format!("{}.{}", self.group_whole(whole), fraction),
+            None => self.group_whole(&text),
+        }
+    }
+
+    fn group_whole(&self, whole: &str) -> String {
+        match whole.strip_prefix('-') {
+            Some(digits) => format!("-{}", group(digits, 3, ',')),
+            None => group(whole, 3, ','),
+        }
+    }
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Number(Number::Integer(_) | Number::Big(_)) => "integer",
+        Value::Number(Number::Float(_)) => "float",
+        Value::Number(Number::Rational(..)) => "rational",
+        Value::Expr(_) => "expression",
+        Value::List(_) => "list",
+        Value::Quantity(_) => "quantity",
+        Value::Date(_) => "date",
+    }
+}
+
+/// Rounds floats to `precision` digits, when set.
+pub fn rounded(n: Number, precision: Option<usize>) -> Number {
+    match precision {
+        Some(digits) => n.round_digits(digits),
+        None => n,
+    }
+}
+
+/// Inserts `separator` between groups of `size` digits, from the right.
+fn group(digits: &str, size: usize, separator: char) -> String {
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(size) {
+            out.push(separator);
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,233 @@
+use core::fmt;
+
+use crate::{
+    errors::SyaError,
+    expr::Expr,
+    number::{Angle, Mode, Number, Rounding},
+};
+
+/// Every built-in function with its signature, for completion and help.
+/// Optional arguments are in brackets.
+pub const FUNCTIONS: &[(&str, &str)] = &[
+    ("diff", "diff(expr, var)"),
+    ("solve", "solve(equation, var[, x0 | a, b])"),
+    ("root", "root(f[, x0 | a, b])"),
+    ("sin", "sin(x)"),
+    ("cos", "cos(x)"),
+    ("tan", "tan(x)"),
+    ("asin", "asin(x)"),
+    ("acos", "acos(x)"),
+    ("atan", "atan(x)"),
+    ("atan2", "atan2(y, x)"),
+    ("sinh", "sinh(x)"),
+    ("cosh", "cosh(x)"),
+    ("tanh", "tanh(x)"),
+    ("exp", "exp(x)"),
+    ("ln", "ln(x)"),
+    ("log10", "log10(x)"),
+    ("log2", "log2(x)"),
+    ("sqrt", "sqrt(x)"),
+    ("cbrt", "cbrt(x)"),
+    ("abs", "abs(x)"),
+    ("sign", "sign(x)"),
+    ("sum", "sum(x, ...)"),
+    ("mean", "mean(x, ...)"),
+    ("median", "median(x, ...)"),
+    ("mode", "mode(x, ...)"),
+    ("min", "min(x, ...)"),
+    ("max", "max(x, ...)"),
+    ("var", "var(x, y, ...)"),
+    ("varp", "varp(x, ...)"),
+    ("stdev", "stdev(x, y, ...)"),
+    ("stdevp", "stdevp(x, ...)"),
+    ("percentile", "percentile(p, x, ...)"),
+    ("len", "len(list)"),
+    ("dot", "dot(a, b)"),
+    ("matmul", "matmul(A, B)"),
+    ("transpose", "transpose(A)"),
+    ("det", "det(A)"),
+    ("inv", "inv(A)"),
+    ("identity", "identity(n)"),
+    ("linsolve", "linsolve(A, b)"),
+    ("weekday", "weekday(date)"),
+    ("round", "round(x[, digits])"),
+    ("roundto", "roundto(x, step)"),
+    ("floor", "floor(x)"),
+    ("ceil", "ceil(x)"),
+    ("trunc", "trunc(x)"),
+    ("frac", "frac(x)"),
+    ("factorial", "factorial(n)"),
+    ("nCr", "nCr(n, r)"),
+    ("nPr", "nPr(n, r)"),
+    ("gcd", "gcd(a, b, ...)"),
+    ("lcm", "lcm(a, b, ...)"),
+    ("isprime", "isprime(n)"),
+    ("nextprime", "nextprime(n)"),
+    ("factor", "factor(n)"),
+    ("modpow", "modpow(base, exp, m)"),
+    ("modinv", "modinv(a, m)"),
+];
+
+/// Calls the built-in function `name`, with angles in `angle` units.
+pub fn call(name: &str, args: &[Number], angle: Angle) -> Result<Number, SyaError> {
+    if !FUNCTIONS.iter().any(|(n, _)| *n == name) {
+        return Err(SyaError::UnknownFunction(name.to_string()));
+    }
+    let x = match (name, args) {
+        ("atan2", [y, x]) => {
+            let turn = y.as_f64().atan2(x.as_f64());
+            return Ok(Number::Float(turn / angle.radians()));
+        }
+        ("atan2", _) | (_, [_, _, ..]) | (_, []) => {
+            return Err(SyaError::InvalidArguments(name.to_string()))
+        }
+        (_, [x]) => x,
+    };
+    let f = x.as_f64();
+    let undefined = || SyaError::OutOfDomain(name.to_string(), x.to_string());
+    let infinite = || SyaError::Pole(name.to_string(), x.to_string());
+
+    let result = match name {
+        "sin" | "cos" | "tan" => return trig(name, x, angle),
+        "asin" | "acos" if !(-1.0..=1.0).contains(&f) => return Err(undefined()),
+        "asin" => f.asin() / angle.radians(),
+        "acos" => f.acos() / angle.radians(),
+        "atan" => f.atan() / angle.radians(),
+        "sinh" => f.sinh(),
+        "cosh" => f.cosh(),
+        "tanh" => f.tanh(),
+        "exp" => f.exp(),
+        "ln" | "log10" | "log2" if f == 0.0 => return Err(infinite()),
+        "ln" | "log10" | "log2" if f < 0.0 => return Err(undefined()),
+        "ln" => f.ln(),
+        "log10" => f.log10(),
+        "log2" => f.log2(),
+        "sqrt" if f < 0.0 => return Err(undefined()),
+        "sqrt" => return Ok(exact_root(x, 2).unwrap_or(Number::Float(f.sqrt()))),
+        "cbrt" => return Ok(exact_root(x, 3).unwrap_or(Number::Float(f.cbrt()))),
+        "abs" if f < 0.0 => return Ok(x.clone().negate()),
+        "abs" => return Ok(x.clone()),
+        "sign" if f.is_nan() => f,
+        "sign" => return Ok(Number::Integer((f > 0.0) as i64 - (f < 0.0) as i64)),
+        _ => return Err(SyaError::UnknownFunction(name.to_string())),
+    };
+    if result.is_infinite() && f.is_finite() {
+        return Err(SyaError::NumberOverflow(format!("{}({})", name, x)));
+    }
+    Ok(Number::Float(result))
+}
+
+/// Calls the rounding function `name`, or returns `None` when `name` isn't
+/// one. `round` and `roundto` settle ties with `rounding`.
+pub fn round(name: &str, args: &[Number], rounding: Rounding) -> Option<Result<Number, SyaError>> {
+    let one = Number::Integer(1);
+    let result = match (name, args) {
+        ("round", [x]) => x.clone().round_to(one, rounding),
+        ("round", [x, digits]) => {
+            decimal_step(digits).and_then(|step| x.clone().round_to(step, rounding))
+        }
+        ("roundto", [x, step]) => x.clone().round_to(step.clone(), rounding),
+        ("floor", [x]) => x.clone().round_to(one, Rounding::Floor),
+        ("ceil", [x]) => x.clone().round_to(one, Rounding::Ceil),
+        ("trunc", [x]) => x.clone().round_to(one, Rounding::TowardZero),
+        ("frac", [x]) => fraction(x),
+        ("round" | "roundto" | "floor" | "ceil" | "trunc" | "frac", _) => {
+            Err(SyaError::InvalidArguments(name.to_string()))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// `10 ^ -digits`, the step of rounding to `digits` after the decimal point.
+fn decimal_step(digits: &Number) -> Result<Number, SyaError> {
+    let digits = match *digits {
+        Number::Integer(d) => d,
+        ref n => return Err(SyaError::NotInteger(n.to_string())),
+    };
+    let overflow = || SyaError::NumberOverflow(format!("10 ^ {}", digits));
+    let power = 10i64
+        .checked_pow(digits.unsigned_abs().try_into().map_err(|_| overflow())?)
+        .ok_or_else(overflow)?;
+    match digits {
+        0.. => Number::rational(1, power),
+        _ => Ok(Number::Integer(power)),
+    }
+}
+
+/// `x` without its whole part, taken as the decimal floats print as so
+/// `frac(2.7)` is 0.7.
+fn fraction(x: &Number) -> Result<Number, SyaError> {
+    let exact = x.clone().in_mode(Mode::Exact)?;
+    let whole = exact
+        .clone()
+        .round_to(Number::Integer(1), Rounding::TowardZero)?;
+    match (x, exact.checked_sub(whole)?) {
+        (Number::Float(_), n) => Ok(Number::Float(n.as_f64())),
+        (_, n) => Ok(n),
+    }
+}
+
+/// Sine, cosine and tangent, exact at quarter turns so `sin(180)` is 0 in
+/// degrees.
+fn trig(name: &str, x: &Number, angle: Angle) -> Result<Number, SyaError> {
+    let quarters = x.as_f64() / angle.quarter();
+    if angle != Angle::Rad && quarters.fract() == 0.0 && quarters.is_finite() {
+        let (sin, cos) = match quarters.rem_euclid(4.0) as i64 {
+            0 => (0, 1),
+            1 => (1, 0),
+            2 => (0, -1),
+            _ => (-1, 0),
+        };
+        return match (name, cos) {
+            ("sin", _) => Ok(Number::Float(sin as f64)),
+            ("cos", _) => Ok(Number::Float(cos as f64)),
+            (_, 0) => Err(SyaError::Pole(name.to_string(), x.to_string())),
+            _ => Ok(Number::Float(0.0)),
+        };
+    }
+
+    let radians = x.as_f64() * angle.radians();
+    Ok(Number::Float(match name {
+        "sin" => radians.sin(),
+        "cos" => radians.cos(),
+        _ => radians.tan(),
+    }))
+}
+
+/// The `n`th root of integers and fractions of perfect powers.
+fn exact_root(x: &Number, n: u32) -> Option<Number> {
+    let root = |i: i64| {
+        let r = (i as f64).abs().powf(1.0 / n as f64).round() as i64 * i.signum();
+        (r.checked_pow(n) == Some(i)).then_some(r)
+    };
+    match *x {
+        Number::Integer(i) => root(i).map(Number::Integer),
+        Number::Rational(num, den) => Number::rational(root(num)?, root(den)?).ok(),
+        Number::Float(_) | Number::Big(_) => None,
+    }
+}
+
+/// A function defined with `name(params) = body`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Expr,
+}
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) = {}",
+            self.name,
+            self.params.join(", "),
+            self.body
+        )
+    }
+}
+impl Function {
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name, self.params.join(", "))
+    }
+}
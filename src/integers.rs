@@ -0,0 +1,270 @@
+use crate::{
+    bigint::{BigInt, MAX_BITS},
+    errors::SyaError,
+    expr::Expr,
+    number::Number,
+};
+
+/// Calls the combinatorics or number theory function `name`, or returns
+/// `None` when `name` isn't one. Results beyond `i64` are big integers, up to
+/// `MAX_BITS`.
+pub fn call(name: &str, args: &[Number]) -> Option<Result<Number, SyaError>> {
+    let result = match name {
+        "factorial" | "nCr" | "nPr" | "gcd" | "lcm" | "isprime" | "nextprime" | "modpow"
+        | "modinv" => integers(name, args).and_then(|args| dispatch(name, &args)),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn dispatch(name: &str, args: &[i64]) -> Result<Number, SyaError> {
+    let undefined = || SyaError::OutOfDomain(name.to_string(), list(args));
+    let overflow = || SyaError::NumberOverflow(format!("{}({})", name, list(args)));
+    let fits = |n: BigInt| match n.bits() > MAX_BITS {
+        true => Err(overflow()),
+        false => Ok(Number::big(n)),
+    };
+    match (name, args) {
+        ("factorial", &[n]) if n >= 0 => fits(product(1, n).ok_or_else(overflow)?),
+        ("nCr", &[n, r]) if n >= 0 && r >= 0 => fits(choose(n, r).ok_or_else(overflow)?),
+        ("nPr", &[n, r]) if n >= 0 && r >= 0 && r <= n => {
+            fits(product(n - r + 1, n).ok_or_else(overflow)?)
+        }
+        ("nPr", &[n, r]) if n >= 0 && r > n => Ok(Number::Integer(0)),
+        ("gcd", [first, rest @ ..]) => {
+            let g = rest.iter().fold(*first as i128, |g, &n| gcd(g, n as i128));
+            fits(BigInt::from(g))
+        }
+        ("lcm", [first, rest @ ..]) => {
+            let mut l = BigInt::from(*first).abs();
+            for &n in rest {
+                let n = (n as i128).abs();
+                // `l % n` is below `n`, so the gcd is taken on machine integers.
+                let (_, rest) = l.div_rem(&BigInt::from(n)).unwrap_or_default();
+                l = match gcd(rest.to_i64().unwrap_or(0) as i128, n) {
+                    0 => BigInt::default(),
+                    g => {
+                        let (quotient, _) = l.div_rem(&BigInt::from(g)).unwrap_or_default();
+                        quotient.mul(&BigInt::from(n))
+                    }
+                };
+                if l.bits() > MAX_BITS {
+                    return Err(overflow());
+                }
+            }
+            fits(l)
+        }
+        ("isprime", &[n]) => Ok(Number::Integer(is_prime(n.max(0) as u64) as i64)),
+        ("nextprime", &[n]) => {
+            let mut candidate = n.max(1);
+            loop {
+                candidate = candidate
+                    .checked_add(1)
+                    .ok_or_else(|| SyaError::NumberOverflow(format!("{}({})", name, list(args))))?;
+                if is_prime(candidate as u64) {
+                    return Ok(Number::Integer(candidate));
+                }
+            }
+        }
+        ("modpow", &[b, e, m]) if e >= 0 && m > 0 => {
+            let power = mod_pow(b.rem_euclid(m) as u64, e as u64, m as u64);
+            Ok(Number::Integer((power % m as u64) as i64))
+        }
+        ("modinv", &[a, m]) if m > 0 => {
+            mod_inverse(a, m).map(Number::Integer).ok_or_else(undefined)
+        }
+        ("factorial", [_]) | ("nCr" | "nPr" | "modinv", [_, _]) | ("modpow", [_, _, _]) => {
+            Err(undefined())
+        }
+        _ => Err(SyaError::InvalidArguments(name.to_string())),
+    }
+}
+
+/// The arguments as integers, floats without a fraction included.
+fn integers(name: &str, args: &[Number]) -> Result<Vec<i64>, SyaError> {
+    if args.is_empty() {
+        return Err(SyaError::InvalidArguments(name.to_string()));
+    }
+    args.iter()
+        .map(|n| match *n {
+            Number::Integer(i) => Ok(i),
+            Number::Float(f) if f.fract() == 0.0 && f.abs() <= i64::MAX as f64 => Ok(f as i64),
+            Number::Big(_) => Err(SyaError::NumberOverflow(n.to_string())),
+            _ => Err(SyaError::NotInteger(n.to_string())),
+        })
+        .collect()
+}
+
+fn list(args: &[i64]) -> String {
+    let args: Vec<String> = args.iter().map(i64::to_string).collect();
+    args.join(", ")
+}
+
+/// `from * (from + 1) * ... * to`, 1 when empty, or `None` past `MAX_BITS`.
+fn product(from: i64, to: i64) -> Option<BigInt> {
+    let mut p = BigInt::from(1i64);
+    for i in from.max(1)..=to {
+        p = p.mul(&BigInt::from(i));
+        if p.bits() > MAX_BITS {
+            return None;
+        }
+    }
+    Some(p)
+}
+
+/// Ways to choose `r` of `n`, each step dividing exactly, or `None` past
+/// `MAX_BITS`.
+fn choose(n: i64, r: i64) -> Option<BigInt> {
+    if r > n {
+        return Some(BigInt::default());
+    }
+    let r = r.min(n - r);
+    let mut c = BigInt::from(1i64);
+    for i in 1..=r {
+        c = c.mul(&BigInt::from(n - r + i)).div_rem(&BigInt::from(i))?.0;
+        if c.bits() > MAX_BITS {
+            return None;
+        }
+    }
+    Some(c)
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// `x` with `a * x = 1 (mod m)`, if `a` and `m` are coprime.
+fn mod_inverse(a: i64, m: i64) -> Option<i64> {
+    let (mut r0, mut r1) = (a.rem_euclid(m) as i128, m as i128);
+    let (mut s0, mut s1) = (1i128, 0i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (s0, s1) = (s1, s0 - q * s1);
+    }
+    (r0 == 1).then(|| s0.rem_euclid(m as i128) as i64)
+}
+
+/// Deterministic Miller-Rabin, these bases cover every 64-bit number.
+fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if let Some(&p) = BASES.iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+    BASES.iter().all(|&a| {
+        let mut x = mod_pow(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+fn mod_pow(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Prime factors of `n` with their powers, smallest first, found with
+/// Pollard's rho.
+fn prime_factors(n: u64) -> Vec<(u64, u32)> {
+    let mut primes = Vec::new();
+    let mut stack = vec![n];
+    while let Some(m) = stack.pop() {
+        match m {
+            1 => {}
+            m if is_prime(m) => primes.push(m),
+            m => {
+                let d = divisor(m);
+                stack.push(d);
+                stack.push(m / d);
+            }
+        }
+    }
+    primes.sort();
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((q, power)) if *q == p => *power += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    factors
+}
+
+/// A nontrivial divisor of the composite `n`.
+fn divisor(n: u64) -> u64 {
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+    for c in 1.. {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y) as i128, n as i128) as u64;
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+/// The prime factorisation of `n` as a product like `2 ^ 3 * 3 ^ 2 * 5`, or
+/// the number itself when it's prime.
+pub fn factor(args: &[Number]) -> Result<Expr, SyaError> {
+    let n = match integers("factor", args)?.as_slice() {
+        &[n] => n,
+        _ => return Err(SyaError::InvalidArguments("factor".to_string())),
+    };
+    if n == 0 {
+        return Err(SyaError::OutOfDomain("factor".to_string(), n.to_string()));
+    }
+    let number = |i: u64| Expr::Number(Number::Integer(i as i64));
+    let mut terms: Vec<Expr> = Vec::new();
+    if n < 0 {
+        terms.push(Expr::Number(Number::Integer(-1)));
+    }
+    for (p, power) in prime_factors(n.unsigned_abs()) {
+        terms.push(match power {
+            1 => number(p),
+            _ => Expr::Binary('^', Box::new(number(p)), Box::new(number(power as u64))),
+        });
+    }
+    Ok(terms
+        .into_iter()
+        .reduce(|product, term| Expr::Binary('*', Box::new(product), Box::new(term)))
+        .unwrap_or(Expr::Number(Number::Integer(1))))
+}
@@ -0,0 +1,72 @@
+use crate::{number::Number, value::Value};
+
+/// Quotes and escapes `s` as a JSON string.
+pub fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn array(items: &[String]) -> String {
+    format!("[{}]", items.join(","))
+}
+
+/// Builds an object from already encoded values, in order.
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// A value with its type, like `{"type":"integer","value":60}`. Floats that
+/// JSON can't represent, like NaN, are strings, and lists and quantities hold
+/// typed values.
+pub fn value(v: &Value) -> String {
+    match v {
+        Value::Number(Number::Integer(i)) => {
+            object(&[("type", string("integer")), ("value", i.to_string())])
+        }
+        Value::Number(Number::Big(b)) => {
+            object(&[("type", string("integer")), ("value", b.to_string())])
+        }
+        Value::Number(Number::Float(f)) if f.is_finite() => {
+            object(&[("type", string("float")), ("value", f.to_string())])
+        }
+        Value::Number(Number::Float(f)) => {
+            object(&[("type", string("float")), ("value", string(&f.to_string()))])
+        }
+        Value::Number(Number::Rational(n, d)) => object(&[
+            ("type", string("rational")),
+            ("value", string(&v.to_string())),
+            ("numerator", n.to_string()),
+            ("denominator", d.to_string()),
+        ]),
+        Value::Expr(e) => object(&[
+            ("type", string("expression")),
+            ("value", string(&e.to_string())),
+        ]),
+        Value::List(items) => {
+            let items: Vec<String> = items.iter().map(value).collect();
+            object(&[("type", string("list")), ("value", array(&items))])
+        }
+        Value::Quantity(q) => object(&[
+            ("type", string("quantity")),
+            ("value", value(&Value::Number(q.value.clone()))),
+            ("unit", string(&q.unit.to_string())),
+        ]),
+        Value::Date(d) => object(&[("type", string("date")), ("value", string(&d.to_string()))]),
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{errors::SyaError, number::Mode, number::Number, value::Value};
+
+/// Calls the list function `name`, or returns `None` when `name` isn't one.
+/// `index` and `slice` are what `v[i]` and `v[start:end]` call.
+pub fn call(name: &str, args: &[Value], mode: Mode) -> Option<Result<Value, SyaError>> {
+    let result = match (name, args) {
+        ("list", items) => Ok(Value::List(items.to_vec())),
+        ("index", [v, i]) => items(v).and_then(|items| {
+            let i = position(i, items.len())?;
+            Ok(items[i].clone())
+        }),
+        ("slice", [v, bounds @ ..]) if (1..=2).contains(&bounds.len()) => {
+            items(v).and_then(|items| slice(items, bounds))
+        }
+        ("len", [v]) => items(v).map(|items| Value::Number(Number::Integer(items.len() as i64))),
+        ("dot", [a, b]) => dot(a, b, mode),
+        ("index" | "slice" | "len" | "dot", _) => Err(SyaError::InvalidArguments(name.to_string())),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn items(v: &Value) -> Result<&[Value], SyaError> {
+    match v {
+        Value::List(items) => Ok(items),
+        v => Err(SyaError::ExpectedList(v.to_string())),
+    }
+}
+
+fn integer(v: &Value) -> Result<i64, SyaError> {
+    match v {
+        Value::Number(Number::Integer(i)) => Ok(*i),
+        Value::Number(n) => Err(SyaError::NotInteger(n.to_string())),
+        v => Err(SyaError::ExpectedNumber(v.to_string())),
+    }
+}
+
+/// The element `i` points at, negative indices counting from the end.
+fn position(i: &Value, len: usize) -> Result<usize, SyaError> {
+    let i = integer(i)?;
+    let from_end = || len.checked_sub(i.unsigned_abs() as usize);
+    match i {
+        0.. if (i as usize) < len => Ok(i as usize),
+        ..0 => from_end().ok_or(SyaError::IndexOutOfRange(i, len)),
+        _ => Err(SyaError::IndexOutOfRange(i, len)),
+    }
+}
+
+/// The elements from `start` up to `end`, or the end of the list. Bounds
+/// beyond either end are clamped, like an empty slice.
+fn slice(items: &[Value], bounds: &[Value]) -> Result<Value, SyaError> {
+    let len = items.len() as i64;
+    let bound = |v: &Value| -> Result<usize, SyaError> {
+        let i = integer(v)?;
+        let i = if i < 0 { i + len } else { i };
+        Ok(i.clamp(0, len) as usize)
+    };
+    let start = bound(&bounds[0])?;
+    let end = match bounds.get(1) {
+        Some(end) => bound(end)?,
+        None => items.len(),
+    };
+    Ok(Value::List(items[start..end.max(start)].to_vec()))
+}
+
+/// Sum of the products of the elements of `a` and `b`.
+fn dot(a: &Value, b: &Value, mode: Mode) -> Result<Value, SyaError> {
+    items(a)?;
+    items(b)?;
+    let products = match a.clone().binary('*', b.clone(), mode)? {
+        Value::List(products) => products,
+        _ => return Err(SyaError::ExpectedList(a.to_string())),
+    };
+    products
+        .into_iter()
+        .try_fold(Value::Number(Number::Integer(0)), |sum, p| {
+            sum.binary('+', p, mode)
+        })
+}
@@ -1,46 +1,526 @@
 #[cfg(test)]
 mod tests;
 
+mod bigint;
+mod cli;
+mod color;
+mod commands;
+mod config;
+mod constants;
+mod dates;
+mod diff;
+mod editor;
 mod errors;
+mod expr;
+mod format;
+mod functions;
+mod integers;
+mod json;
+mod lists;
+mod matrix;
 mod number;
+mod simplify;
+mod solve;
+mod stats;
 mod sya;
 mod tokenizer;
+mod trace;
+mod units;
+mod value;
 
-use std::io::{self, Write};
+use std::{
+    env, fs,
+    io::{self, BufRead, IsTerminal},
+    process,
+};
 
+use cli::Source;
+use commands::{parse_command, Command};
+use config::Config;
+use editor::Editor;
+use errors::SyaError;
+use format::Format;
+use simplify::SimplifyOptions;
 use sya::Sya;
+use units::Units;
+use value::Value;
+
+const EXIT_USAGE: i32 = 1;
+const EXIT_PARSE: i32 = 2;
+const EXIT_CALCULATE: i32 = 3;
+
+/// State of the prompt that isn't part of the evaluator.
+struct Settings {
+    echo_rpn: bool,
+    /// Print a JSON object for each input instead of the result.
+    json: bool,
+    /// Highlight the input and errors, see `color::enabled`.
+    color: bool,
+    prompt: String,
+    format: Format,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            echo_rpn: true,
+            json: false,
+            color: false,
+            prompt: "> ".to_string(),
+            format: Format::default(),
+        }
+    }
+}
+
+enum Outcome {
+    Value(Value),
+    /// A command that printed its own output.
+    Done,
+    Quit,
+}
+
+enum Failure {
+    Command(String),
+    Parse(SyaError),
+    Calculate(SyaError),
+}
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Command(_) => EXIT_USAGE,
+            Failure::Parse(_) => EXIT_PARSE,
+            Failure::Calculate(_) => EXIT_CALCULATE,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Failure::Command(e) => e.clone(),
+            Failure::Parse(e) | Failure::Calculate(e) => e.to_string(),
+        }
+    }
+
+    /// Prints the error, in colour with the part of `input` at fault when
+    /// `color` is set.
+    fn report(&self, color: bool, input: &str, span: Option<(usize, usize)>) {
+        let (title, e) = match self {
+            Failure::Command(e) if color => return eprintln!("{}", color::error(e)),
+            Failure::Command(e) => return eprintln!("{}", e),
+            Failure::Parse(e) => ("Error parsing input:", e),
+            Failure::Calculate(e) => ("Error calculating expression:", e),
+        };
+        if !color {
+            return eprintln!("{}\n{}", title, e);
+        }
+        eprintln!("{}\n{}", color::error(title), e);
+        if let Some(span) = span {
+            eprintln!("  {}", color::mark(input, span));
+        }
+    }
+}
 
 fn main() {
-    let mut sya = Sya::new("").expect("Should construct");
-    println!("q! for exit");
-    loop {
-        let mut input = String::new();
-        print!("> ");
-        io::stdout().flush().expect("Error flushing stdout");
-        io::stdin()
-            .read_line(&mut input)
-            .expect("error: unable to read input");
+    let (source, options) = match cli::parse_args(env::args().skip(1)) {
+        Ok(cli::Command::Run(source, options)) => (source, options),
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
 
-        if input.trim() == "q!".to_string() {
-            break;
+    let config = match options.no_config {
+        true => Config::default(),
+        false => config::load().unwrap_or_else(|e| {
+            eprintln!("Error in config {}", e);
+            Config::default()
+        }),
+    };
+    let mut sya = Sya::new("").expect("Should construct");
+    sya.mode = config.mode;
+    sya.angle = config.angle;
+    sya.rounding = config.rounding;
+    sya.solver = config.solver.clone();
+    if !options.no_config {
+        sya.units = units::load().unwrap_or_else(|e| {
+            eprintln!("Error in units {}", e);
+            Units::default()
+        });
+    }
+    for definition in &config.definitions {
+        if let Err(e) = sya.define(definition) {
+            eprintln!("Error in config definition '{}':\n{}", definition, e);
+        }
+    }
+    let mut settings = Settings {
+        echo_rpn: config.echo_rpn,
+        json: options.json,
+        color: config
+            .color
+            .unwrap_or_else(|| color::enabled(&io::stdout()) && color::enabled(&io::stderr())),
+        prompt: config.prompt,
+        format: config.format,
+    };
+    sya.precision = settings.format.precision;
+    if let Some(radix) = options.radix {
+        settings.format.radix = radix;
+    }
+    settings.format.group |= options.group;
+    settings.format.types |= options.types;
+    settings.format.fractions &= !options.decimals;
+    let code = match source {
+        Source::Stdin if io::stdin().is_terminal() && !settings.json => {
+            repl(&mut sya, &mut settings);
+            0
         }
+        Source::Stdin => {
+            let lines = io::stdin().lock().lines().map_while(Result::ok);
+            run_lines(&mut sya, &mut settings, lines)
+        }
+        Source::Args(expressions) => run_lines(&mut sya, &mut settings, expressions.into_iter()),
+        Source::Script(path) => run_script(&mut sya, &mut settings, &path, options.verbose),
+        Source::File(path) => match fs::read_to_string(&path) {
+            Ok(content) => run_lines(&mut sya, &mut settings, content.lines().map(str::to_string)),
+            Err(e) => {
+                eprintln!("Error reading '{}': {}", path, e);
+                EXIT_USAGE
+            }
+        },
+    };
+    process::exit(code);
+}
 
-        match sya.new_input(&input.trim()) {
-            Ok(_) => {}
+fn repl(sya: &mut Sya, settings: &mut Settings) {
+    println!("q! or :quit for exit, :help for commands");
+    let mut editor = Editor::new();
+    if settings.color {
+        let units = sya.units.clone();
+        editor.highlight = Some(Box::new(move |line, cursor| {
+            color::highlight(line, cursor, &units)
+        }));
+    }
+    loop {
+        editor.completions = completions(sya);
+        let input = match editor.read_line(&settings.prompt) {
+            Ok(Some(input)) => input,
+            Ok(None) => break,
             Err(e) => {
-                eprintln!("Error parsing input:\n{}", e);
-                continue;
+                eprintln!("error: unable to read input: {}", e);
+                break;
             }
         };
 
-        match sya.calculate() {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("Error calculating expression:\n{}", e);
-                continue;
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        match run(sya, settings, input.trim()) {
+            Ok(Outcome::Value(value)) => {
+                if settings.echo_rpn {
+                    println!("RPN: {}", sya.rpn_formatted());
+                }
+                print_trace(sya);
+                println!("Result: {}", settings.format.value(&value));
             }
+            Ok(Outcome::Done) => {}
+            Ok(Outcome::Quit) => break,
+            Err(f) => f.report(settings.color, input.trim(), sya.error_span),
         }
-        println!("RPN: {}", sya.rpn_formatted());
-        println!("Result: {:?}", &sya.out.as_ref().unwrap());
+    }
+}
+
+/// Variables, constants, functions and commands for Tab. Functions complete with their
+/// open paren and commands with their arguments as description.
+fn completions(sya: &Sya) -> Vec<(String, String)> {
+    let mut vars: Vec<String> = sya.vars.keys().cloned().collect();
+    if !sya.results.is_empty() {
+        vars.push("ans".to_string());
+    }
+    vars.sort();
+    let vars = vars.into_iter().map(|v| (v.clone(), v));
+    let constants = constants::CONSTANTS
+        .iter()
+        .map(|(name, _, description)| (name.to_string(), description.to_string()));
+    let mut user: Vec<_> = sya.functions.values().collect();
+    user.sort_by(|a, b| a.name.cmp(&b.name));
+    let user = user
+        .into_iter()
+        .map(|f| (format!("{}(", f.name), f.signature()));
+    let functions = functions::FUNCTIONS
+        .iter()
+        .map(|(name, signature)| (format!("{}(", name), signature.to_string()))
+        .chain(user);
+    let commands = commands::COMMANDS
+        .iter()
+        .map(|(name, args, _)| (name.to_string(), format!("{} {}", name, args)));
+    vars.chain(constants)
+        .chain(functions)
+        .chain(commands)
+        .collect()
+}
+
+/// Evaluates every non-empty line without prompts, printing only the results.
+/// Returns the exit code of the first failure, or 0.
+fn run_lines<I: Iterator<Item = String>>(sya: &mut Sya, settings: &mut Settings, lines: I) -> i32 {
+    let mut code = 0;
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let outcome = run(sya, settings, line.trim());
+        if let Err(f) = &outcome {
+            if code == 0 {
+                code = f.exit_code();
+            }
+        }
+        if settings.json {
+            println!(
+                "{}",
+                json_outcome(sya, &settings.format, line.trim(), &outcome)
+            );
+            if let Ok(Outcome::Quit) = outcome {
+                break;
+            }
+            continue;
+        }
+        match outcome {
+            Ok(Outcome::Value(value)) => {
+                print_trace(sya);
+                println!("{}", settings.format.value(&value));
+            }
+            Ok(Outcome::Done) => {}
+            Ok(Outcome::Quit) => break,
+            Err(f) => f.report(settings.color, line.trim(), sya.error_span),
+        }
+    }
+    code
+}
+
+/// Runs each line of the script at `path`, skipping blank and comment lines,
+/// and stops at the first error, reported as `path:line:column`. Prints the
+/// last value, or every value when `verbose`.
+fn run_script(sya: &mut Sya, settings: &mut Settings, path: &str, verbose: bool) -> i32 {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading '{}': {}", path, e);
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut last = None;
+    for (number, line) in content.lines().enumerate() {
+        let input = line.trim();
+        if input.is_empty() || input.starts_with('#') {
+            continue;
+        }
+        match run(sya, settings, input) {
+            Ok(Outcome::Value(value)) => {
+                let value = settings.format.value(&value);
+                if verbose {
+                    println!("{}", value);
+                }
+                last = Some(value);
+            }
+            Ok(Outcome::Done) => {}
+            Ok(Outcome::Quit) => break,
+            Err(f) => {
+                let leading = line.chars().count() - line.trim_start().chars().count();
+                let column = leading + sya.error_span.map_or(0, |(start, _)| start) + 1;
+                let location = format!("{}:{}:{}:", path, number + 1, column);
+                match settings.color {
+                    true => eprintln!("{} {}", color::error(&location), f.message()),
+                    false => eprintln!("{} {}", location, f.message()),
+                }
+                return f.exit_code();
+            }
+        }
+    }
+    if let (Some(value), false) = (last, verbose) {
+        println!("{}", value);
+    }
+    0
+}
+
+/// Runs a meta-command or one line of input.
+fn run(sya: &mut Sya, settings: &mut Settings, input: &str) -> Result<Outcome, Failure> {
+    let command = match parse_command(input).map_err(Failure::Command)? {
+        Some(command) => command,
+        None => return calculate(sya, input, false),
+    };
+
+    match command {
+        Command::Help => println!("{}", commands::help()),
+        Command::Quit => return Ok(Outcome::Quit),
+        Command::Rpn(on) => settings.echo_rpn = on,
+        Command::Vars => {
+            let mut vars: Vec<_> = sya.vars.iter().collect();
+            vars.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, value) in vars {
+                println!("{} = {}", name, settings.format.value(value));
+            }
+            let mut functions: Vec<_> = sya.functions.values().collect();
+            functions.sort_by(|a, b| a.name.cmp(&b.name));
+            for function in functions {
+                println!("{}", function);
+            }
+        }
+        Command::Clear => {
+            sya.vars.clear();
+            sya.functions.clear();
+            sya.results.clear();
+        }
+        Command::History => {
+            for (i, value) in sya.results.iter().enumerate() {
+                println!("${} = {}", i + 1, settings.format.value(value));
+            }
+        }
+        Command::Mode(mode) => sya.mode = mode,
+        Command::Precision(precision) => {
+            settings.format.precision = precision;
+            sya.precision = precision;
+        }
+        Command::Angle(angle) => sya.angle = angle,
+        Command::Rounding(rounding) => sya.rounding = rounding,
+        Command::Tolerance(tolerance) => sya.solver.tolerance = tolerance,
+        Command::Iterations(n) => sya.solver.max_iterations = n,
+        Command::Radix(radix) => settings.format.radix = radix,
+        Command::Group(on) => settings.format.group = on,
+        Command::Types(on) => settings.format.types = on,
+        Command::Fractions(on) => settings.format.fractions = on,
+        Command::Show(name) => match name {
+            ":mode" => println!("{}", sya.mode),
+            ":angle" => println!("{}", sya.angle),
+            ":rounding" => println!("{}", sya.rounding),
+            ":tolerance" => println!("{}", sya.solver.tolerance),
+            ":iterations" => println!("{}", sya.solver.max_iterations),
+            _ => match settings.format.precision {
+                Some(digits) => println!("{}", digits),
+                None => println!("off"),
+            },
+        },
+        Command::Trace(expr) => {
+            return calculate(sya, expr, true).inspect_err(|_| shift_span(sya, input, expr))
+        }
+        Command::Simplify(expr, aggressive) => {
+            let options = SimplifyOptions {
+                aggressive,
+                mode: sya.mode,
+            };
+            sya.new_input(expr)
+                .inspect_err(|_| shift_span(sya, input, expr))
+                .map_err(Failure::Parse)?;
+            let simplified = sya.simplified(&options).map_err(Failure::Calculate)?;
+            println!("{}", simplified);
+        }
+    }
+    Ok(Outcome::Done)
+}
+
+fn calculate(sya: &mut Sya, input: &str, tracing: bool) -> Result<Outcome, Failure> {
+    sya.set_trace(tracing);
+    sya.new_input(input).map_err(Failure::Parse)?;
+    sya.calculate().map_err(|e| match e.is_syntax() {
+        true => Failure::Parse(e),
+        false => Failure::Calculate(e),
+    })?;
+    Ok(Outcome::Value(sya.out.clone().unwrap()))
+}
+
+/// Describes the tokens, RPN and result or error of one input. Commands only
+/// have their input, and errors of commands their message.
+fn json_outcome(
+    sya: &Sya,
+    format: &Format,
+    input: &str,
+    outcome: &Result<Outcome, Failure>,
+) -> String {
+    let mut fields = vec![("input", json::string(input))];
+    let expression = matches!(
+        outcome,
+        Ok(Outcome::Value(_)) | Err(Failure::Parse(_) | Failure::Calculate(_))
+    );
+    if expression {
+        let tokens: Vec<String> = sya
+            .statements
+            .iter()
+            .enumerate()
+            .flat_map(|(i, tokens)| {
+                let separator = (i > 0).then(|| ";".to_string());
+                separator
+                    .into_iter()
+                    .chain(tokens.iter().map(|t| t.to_string()))
+            })
+            .map(|t| json::string(&t))
+            .collect();
+        fields.push(("tokens", json::array(&tokens)));
+        fields.push(("rpn", json::string(&sya.rpn_formatted())));
+    }
+
+    let (stage, error) = match outcome {
+        Ok(Outcome::Value(value)) => {
+            fields.push((
+                "result",
+                json::value(&rounded(value.clone(), format.precision)),
+            ));
+            return json::object(&fields);
+        }
+        Ok(_) => return json::object(&fields),
+        Err(Failure::Command(message)) => {
+            let error = json::object(&[
+                ("stage", json::string("command")),
+                ("kind", json::string("Command")),
+                ("message", json::string(message)),
+                ("span", "null".to_string()),
+            ]);
+            fields.push(("error", error));
+            return json::object(&fields);
+        }
+        Err(Failure::Parse(e)) => ("parse", e),
+        Err(Failure::Calculate(e)) => ("calculate", e),
+    };
+    let span = match sya.error_span {
+        Some((start, end)) => json::array(&[start.to_string(), end.to_string()]),
+        None => "null".to_string(),
+    };
+    fields.push((
+        "error",
+        json::object(&[
+            ("stage", json::string(stage)),
+            ("kind", json::string(error.kind())),
+            ("message", json::string(&error.to_string())),
+            ("span", span),
+        ]),
+    ));
+    json::object(&fields)
+}
+
+/// Moves the error span of `expr` to where it is in `input`, which ends
+/// with it.
+fn shift_span(sya: &mut Sya, input: &str, expr: &str) {
+    let offset = input.chars().count() - expr.chars().count();
+    if let Some((start, end)) = sya.error_span {
+        sya.error_span = Some((start + offset, end + offset));
+    }
+}
+
+fn rounded(value: Value, precision: Option<usize>) -> Value {
+    match value {
+        Value::Number(n) => Value::Number(format::rounded(n, precision)),
+        Value::List(items) => {
+            Value::List(items.into_iter().map(|v| rounded(v, precision)).collect())
+        }
+        Value::Quantity(q) => Value::Quantity(Box::new(
+            q.with_value(format::rounded(q.value.clone(), precision)),
+        )),
+        v => v,
+    }
+}
+
+fn print_trace(sya: &Sya) {
+    if let Some(trace) = &sya.trace {
+        println!("{}\n", trace.tables());
     }
 }
@@ -0,0 +1,265 @@
+use crate::{
+    errors::SyaError,
+    number::{Mode, Number},
+    value::Value,
+};
+
+/// Rows of numbers, all of the same length.
+type Matrix = Vec<Vec<Number>>;
+
+/// Largest `n` for `identity(n)`, which would otherwise try to allocate
+/// any size asked for.
+pub const MAX_SIZE: i64 = 1000;
+
+/// Calls the matrix function `name`, or returns `None` when `name` isn't one.
+/// Matrices are lists of rows, and a flat list is a column vector on the
+/// right of `matmul` or `linsolve` and a row vector on the left.
+pub fn call(name: &str, args: &[Value], mode: Mode) -> Option<Result<Value, SyaError>> {
+    let result = match (name, args) {
+        ("matmul", [a, b]) => matmul(a, b, mode),
+        ("transpose", [a]) => matrix(a).map(|a| value(transpose(&a))),
+        ("det", [a]) => square(a).and_then(|a| determinant(a, mode)),
+        ("inv", [a]) => square(a).and_then(|a| {
+            let n = a.len();
+            solve(a, identity(n), mode).map(value)
+        }),
+        ("linsolve", [a, b]) => linsolve(a, b, mode),
+        ("identity", [n]) => match n {
+            Value::Number(Number::Integer(n)) if *n > MAX_SIZE => Err(SyaError::MatrixTooLarge(*n)),
+            Value::Number(Number::Integer(n)) if *n > 0 => Ok(value(identity(*n as usize))),
+            n => Err(SyaError::OutOfDomain(name.to_string(), n.to_string())),
+        },
+        ("matmul" | "transpose" | "det" | "inv" | "linsolve" | "identity", _) => {
+            Err(SyaError::InvalidArguments(name.to_string()))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// The rows of `v`, a flat list being a single row.
+fn matrix(v: &Value) -> Result<Matrix, SyaError> {
+    let not_matrix = || SyaError::ExpectedMatrix(v.to_string());
+    let rows = match v {
+        Value::List(rows) if !rows.is_empty() => rows,
+        _ => return Err(not_matrix()),
+    };
+    let row = |r: &Value| match r {
+        Value::Number(n) => Ok(vec![n.clone()]),
+        Value::List(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Number(n) => Ok(n.clone()),
+                _ => Err(not_matrix()),
+            })
+            .collect(),
+        _ => Err(not_matrix()),
+    };
+    match rows.iter().all(|r| matches!(r, Value::Number(_))) {
+        true => Ok(vec![row(v)?]),
+        false => {
+            let m: Matrix = rows.iter().map(row).collect::<Result<_, _>>()?;
+            match m.iter().all(|r| r.len() == m[0].len() && !r.is_empty()) {
+                true => Ok(m),
+                false => Err(not_matrix()),
+            }
+        }
+    }
+}
+
+/// Whether `v` is a list of rows, which `*` multiplies as matrices.
+pub fn is_matrix(v: &Value) -> bool {
+    matches!(v, Value::List(rows) if !rows.is_empty() && rows.iter().all(|r| matches!(r, Value::List(_))))
+}
+
+fn is_vector(v: &Value) -> bool {
+    matches!(v, Value::List(items) if items.iter().all(|i| matches!(i, Value::Number(_))))
+}
+
+fn value(m: Matrix) -> Value {
+    Value::List(
+        m.into_iter()
+            .map(|row| Value::List(row.into_iter().map(Value::Number).collect()))
+            .collect(),
+    )
+}
+
+/// A column vector as a flat list.
+fn column(m: Matrix) -> Value {
+    Value::List(m.into_iter().flatten().map(Value::Number).collect())
+}
+
+fn size(m: &Matrix) -> (usize, usize) {
+    (m.len(), m[0].len())
+}
+
+fn square(v: &Value) -> Result<Matrix, SyaError> {
+    let m = matrix(v)?;
+    match size(&m) {
+        (rows, cols) if rows == cols => Ok(m),
+        (rows, cols) => Err(SyaError::NotSquare(rows, cols)),
+    }
+}
+
+fn identity(n: usize) -> Matrix {
+    (0..n)
+        .map(|i| (0..n).map(|j| Number::Integer((i == j) as i64)).collect())
+        .collect()
+}
+
+fn transpose(m: &Matrix) -> Matrix {
+    (0..m[0].len())
+        .map(|j| m.iter().map(|row| row[j].clone()).collect())
+        .collect()
+}
+
+pub fn matmul(a: &Value, b: &Value, mode: Mode) -> Result<Value, SyaError> {
+    let left = matrix(a)?;
+    let right = match is_vector(b) {
+        true => transpose(&matrix(b)?),
+        false => matrix(b)?,
+    };
+    if size(&left).1 != size(&right).0 {
+        return Err(SyaError::DimensionMismatch(size(&left), size(&right)));
+    }
+    let product: Matrix = left
+        .iter()
+        .map(|row| {
+            (0..right[0].len())
+                .map(|j| {
+                    row.iter()
+                        .zip(&right)
+                        .try_fold(Number::Integer(0), |sum, (x, r)| {
+                            let term = x.clone().checked_op_in('*', r[j].clone(), mode)?;
+                            sum.checked_op_in('+', term, mode)
+                        })
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()?;
+    match (is_vector(a), is_vector(b)) {
+        (true, _) => Ok(Value::List(
+            product[0].iter().cloned().map(Value::Number).collect(),
+        )),
+        (_, true) => Ok(column(product)),
+        _ => Ok(value(product)),
+    }
+}
+
+fn linsolve(a: &Value, b: &Value, mode: Mode) -> Result<Value, SyaError> {
+    let a = square(a)?;
+    let rhs = match is_vector(b) {
+        true => transpose(&matrix(b)?),
+        false => matrix(b)?,
+    };
+    if size(&rhs).0 != a.len() {
+        return Err(SyaError::DimensionMismatch(size(&a), size(&rhs)));
+    }
+    let x = solve(a, rhs, mode)?;
+    match is_vector(b) {
+        true => Ok(column(x)),
+        false => Ok(value(x)),
+    }
+}
+
+fn determinant(a: Matrix, mode: Mode) -> Result<Value, SyaError> {
+    let (det, _) = exact_or_float(a, Vec::new(), reduce)?;
+    det.settle(mode).map(Value::Number)
+}
+
+/// `x` with `a * x = b`.
+fn solve(a: Matrix, b: Matrix, mode: Mode) -> Result<Matrix, SyaError> {
+    let (det, x) = exact_or_float(a, b, reduce)?;
+    if det.as_f64() == 0.0 {
+        return Err(SyaError::Singular);
+    }
+    x.into_iter()
+        .map(|row| row.into_iter().map(|n| n.settle(mode)).collect())
+        .collect()
+}
+
+/// Runs `f` on exact numbers, and again on floats when the fractions
+/// overflow.
+fn exact_or_float(
+    mut a: Matrix,
+    mut b: Matrix,
+    f: impl Fn(&mut Matrix, &mut Matrix) -> Result<Number, SyaError>,
+) -> Result<(Number, Matrix), SyaError> {
+    let (a0, b0) = (a.clone(), b.clone());
+    match f(&mut a, &mut b) {
+        Err(SyaError::NumberOverflow(_)) => {
+            let float = |m: Matrix| -> Matrix {
+                m.into_iter()
+                    .map(|row| row.iter().map(|n| Number::Float(n.as_f64())).collect())
+                    .collect()
+            };
+            let (mut a, mut b) = (float(a0), float(b0));
+            let det = f(&mut a, &mut b)?;
+            Ok((det, b))
+        }
+        det => Ok((det?, b)),
+    }
+}
+
+/// Reduces `a` to the identity with Gauss-Jordan elimination and partial
+/// pivoting, applying the same row operations to `b`. Returns the determinant
+/// of `a`, 0 when it's singular and the reduction stops.
+fn reduce(a: &mut Matrix, b: &mut Matrix) -> Result<Number, SyaError> {
+    let op =
+        |x: &Number, sign: char, y: &Number| x.clone().checked_op_in(sign, y.clone(), Mode::Exact);
+    let n = a.len();
+    let scale = a
+        .iter()
+        .flatten()
+        .map(|x| x.as_f64().abs())
+        .fold(0.0, f64::max);
+    let is_zero = |x: &Number| match x {
+        Number::Float(f) => f.abs() <= scale * n as f64 * f64::EPSILON,
+        x => x.as_f64() == 0.0,
+    };
+    let mut det = Number::Integer(1);
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| {
+                a[i][col]
+                    .as_f64()
+                    .abs()
+                    .total_cmp(&a[j][col].as_f64().abs())
+            })
+            .unwrap();
+        if is_zero(&a[pivot][col]) {
+            return Ok(Number::Integer(0));
+        }
+        if pivot != col {
+            a.swap(pivot, col);
+            if !b.is_empty() {
+                b.swap(pivot, col);
+            }
+            det = det.negate();
+        }
+        let p = a[col][col].clone();
+        det = op(&det, '*', &p)?;
+        for x in a[col]
+            .iter_mut()
+            .chain(b.get_mut(col).into_iter().flatten())
+        {
+            *x = op(x, '/', &p)?;
+        }
+        for row in 0..n {
+            let factor = a[row][col].clone();
+            if row == col || factor.as_f64() == 0.0 {
+                continue;
+            }
+            let pivot_row = a[col].clone();
+            for (x, y) in a[row].iter_mut().zip(&pivot_row) {
+                *x = op(x, '-', &op(&factor, '*', y)?)?;
+            }
+            if let Some(pivot_row) = b.get(col).cloned() {
+                for (x, y) in b[row].iter_mut().zip(&pivot_row) {
+                    *x = op(x, '-', &op(&factor, '*', y)?)?;
+                }
+            }
+        }
+    }
+    Ok(det)
+}
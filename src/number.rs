@@ -1,3 +1,4 @@
+use crate::bigint::{BigInt, MAX_BITS};
 use crate::errors::SyaError;
 use core::fmt;
 
@@ -5,22 +6,443 @@ use core::fmt;
 pub enum Number {
     Integer(i64),
     Float(f64),
+    /// Always normalized, with a denominator greater than 1.
+    Rational(i64, i64),
+    /// An integer outside `i64`, from functions like `factorial` whose
+    /// results grow fast.
+    Big(BigInt),
 }
 impl fmt::Display for Number {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Number::Integer(i) => write!(f, "{}", i),
             Number::Float(fl) => write!(f, "{}", fl),
+            Number::Rational(n, d) => write!(f, "{}/{}", n, d),
+            Number::Big(b) => write!(f, "{}", b),
         }
     }
 }
+
+/// How numbers are treated during evaluation.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Mode {
+    /// Integers stay integers while results are exact, floats otherwise.
+    #[default]
+    Auto,
+    /// Every value is an integer, division truncates.
+    Int,
+    /// Every value is a float.
+    Float,
+    /// Division and decimal literals give fractions instead of floats.
+    Exact,
+}
+impl Mode {
+    pub fn parse(s: &str) -> Option<Mode> {
+        match s {
+            "auto" => Some(Mode::Auto),
+            "int" => Some(Mode::Int),
+            "float" => Some(Mode::Float),
+            "exact" => Some(Mode::Exact),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Auto => write!(f, "auto"),
+            Mode::Int => write!(f, "int"),
+            Mode::Float => write!(f, "float"),
+            Mode::Exact => write!(f, "exact"),
+        }
+    }
+}
+
+/// Unit of the angles taken and returned by trigonometric functions.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Angle {
+    #[default]
+    Rad,
+    Deg,
+    Grad,
+}
+impl Angle {
+    pub fn parse(s: &str) -> Option<Angle> {
+        match s {
+            "rad" => Some(Angle::Rad),
+            "deg" => Some(Angle::Deg),
+            "grad" => Some(Angle::Grad),
+            _ => None,
+        }
+    }
+
+    /// Size of a quarter turn.
+    pub fn quarter(self) -> f64 {
+        match self {
+            Angle::Rad => std::f64::consts::FRAC_PI_2,
+            Angle::Deg => 90.0,
+            Angle::Grad => 100.0,
+        }
+    }
+
+    /// Radians in one unit.
+    pub fn radians(self) -> f64 {
+        std::f64::consts::FRAC_PI_2 / self.quarter()
+    }
+}
+impl fmt::Display for Angle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Angle::Rad => write!(f, "rad"),
+            Angle::Deg => write!(f, "deg"),
+            Angle::Grad => write!(f, "grad"),
+        }
+    }
+}
+
+/// How `round` and `roundto` settle ties, and the direction of `floor`,
+/// `ceil` and `trunc`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Rounding {
+    /// Ties toward positive infinity.
+    HalfUp,
+    /// Ties to the even neighbour.
+    HalfEven,
+    /// Ties away from zero.
+    #[default]
+    HalfAway,
+    TowardZero,
+    Floor,
+    Ceil,
+}
+impl Rounding {
+    pub fn parse(s: &str) -> Option<Rounding> {
+        match s {
+            "half-up" => Some(Rounding::HalfUp),
+            "half-even" => Some(Rounding::HalfEven),
+            "half-away" => Some(Rounding::HalfAway),
+            "toward-zero" => Some(Rounding::TowardZero),
+            "floor" => Some(Rounding::Floor),
+            "ceil" => Some(Rounding::Ceil),
+            _ => None,
+        }
+    }
+
+    /// The integer `n / d` rounds to, `d` being positive.
+    fn quotient(self, n: i128, d: i128) -> i128 {
+        let (below, rest) = (n.div_euclid(d), n.rem_euclid(d));
+        let half = (2 * rest).cmp(&d);
+        let up = match self {
+            _ if rest == 0 => false,
+            Rounding::Floor => false,
+            Rounding::Ceil => true,
+            Rounding::TowardZero => n < 0,
+            Rounding::HalfUp => half.is_ge(),
+            Rounding::HalfAway => half.is_gt() || half.is_eq() && n > 0,
+            Rounding::HalfEven => half.is_gt() || half.is_eq() && below % 2 != 0,
+        };
+        below + up as i128
+    }
+
+    fn float(self, f: f64) -> f64 {
+        match self {
+            Rounding::Floor => f.floor(),
+            Rounding::Ceil => f.ceil(),
+            Rounding::TowardZero => f.trunc(),
+            Rounding::HalfUp if f - f.floor() >= 0.5 => f.floor() + 1.0,
+            Rounding::HalfUp => f.floor(),
+            Rounding::HalfEven => f.round_ties_even(),
+            Rounding::HalfAway => f.round(),
+        }
+    }
+}
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::HalfUp => write!(f, "half-up"),
+            Rounding::HalfEven => write!(f, "half-even"),
+            Rounding::HalfAway => write!(f, "half-away"),
+            Rounding::TowardZero => write!(f, "toward-zero"),
+            Rounding::Floor => write!(f, "floor"),
+            Rounding::Ceil => write!(f, "ceil"),
+        }
+    }
+}
+
 impl Number {
-    pub fn as_u32(self) -> Result<u32, SyaError> {
+    /// An integer, big only when it doesn't fit in `i64`.
+    pub fn big(b: BigInt) -> Self {
+        match b.to_i64() {
+            Some(i) => Number::Integer(i),
+            None => Number::Big(b),
+        }
+    }
+
+    /// Builds a normalized fraction, or an integer when `d` divides `n`.
+    pub fn rational(n: i64, d: i64) -> Result<Self, SyaError> {
+        Number::from_ratio(n as i128, d as i128)
+    }
+
+    fn from_ratio(n: i128, d: i128) -> Result<Self, SyaError> {
+        if d == 0 {
+            return Err(SyaError::DivisionByZero);
+        }
+        let g = gcd(n.unsigned_abs(), d.unsigned_abs()) as i128;
+        let (n, d) = (n / g * d.signum(), d.abs() / g);
+        let overflow = || SyaError::NumberOverflow(format!("{}/{}", n, d));
+        let n = i64::try_from(n).map_err(|_| overflow())?;
+        let d = i64::try_from(d).map_err(|_| overflow())?;
+        match d {
+            1 => Ok(Number::Integer(n)),
+            _ => Ok(Number::Rational(n, d)),
+        }
+    }
+
+    /// Numerator and denominator of exact numbers.
+    fn ratio(&self) -> Option<(i128, i128)> {
+        match *self {
+            Number::Integer(i) => Some((i as i128, 1)),
+            Number::Rational(n, d) => Some((n as i128, d as i128)),
+            Number::Float(_) | Number::Big(_) => None,
+        }
+    }
+
+    /// Integers of either size.
+    fn whole(&self) -> Option<BigInt> {
+        match self {
+            Number::Integer(i) => Some(BigInt::from(*i)),
+            Number::Big(b) => Some(b.clone()),
+            _ => None,
+        }
+    }
+
+    /// Operations with a big integer, exact unless a division leaves a
+    /// remainder, which `truncate` drops. Fractions turn to floats.
+    fn big_op(&self, sign: char, other: &Self, truncate: bool) -> Result<Self, SyaError> {
+        let (a, b) = match (self.whole(), other.whole()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                return Number::Float(self.as_f64()).checked_op(sign, Number::Float(other.as_f64()))
+            }
+        };
+        let overflow = || SyaError::NumberOverflow(format!("{} {} {}", self, sign, other));
+        if sign == '*' && a.bits() + b.bits() > MAX_BITS {
+            return Err(overflow());
+        }
+        let n = match sign {
+            '+' => a.add(&b),
+            '-' => a.sub(&b),
+            '*' => a.mul(&b),
+            '/' => match a.div_rem(&b).ok_or(SyaError::DivisionByZero)? {
+                (q, r) if truncate || r.is_zero() => q,
+                _ => return Ok(Number::Float(a.to_f64() / b.to_f64())),
+            },
+            _ => return Err(SyaError::InvalidOperation(sign)),
+        };
+        match n.bits() > MAX_BITS {
+            true => Err(overflow()),
+            false => Ok(Number::big(n)),
+        }
+    }
+
+    fn ratio_op(&self, sign: char, other: &Self) -> Result<Self, SyaError> {
+        let overflow = || SyaError::NumberOverflow(format!("{} {} {}", self, sign, other));
+        let ((an, ad), (bn, bd)) = match (self.ratio(), other.ratio()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Err(SyaError::InvalidOperation(sign)),
+        };
+        let cross = |x: i128, y: i128| x.checked_mul(y).ok_or_else(overflow);
+        let (n, d) = match sign {
+            '+' => (cross(an, bd)?.checked_add(cross(bn, ad)?), ad * bd),
+            '-' => (cross(an, bd)?.checked_sub(cross(bn, ad)?), ad * bd),
+            '*' => (Some(an * bn), ad * bd),
+            '/' => (Some(an * bd), ad * bn),
+            _ => return Err(SyaError::InvalidOperation(sign)),
+        };
+        Number::from_ratio(n.ok_or_else(overflow)?, d)
+    }
+
+    /// The simplest fraction within `tolerance` of `x`, if one fits.
+    pub fn approximate(x: f64, tolerance: f64) -> Option<Self> {
+        let (mut h, mut h1, mut k, mut k1) = (1i64, 0i64, 0i64, 1i64);
+        let mut y = x;
+        for _ in 0..64 {
+            let a = y.floor();
+            if !a.is_finite() || a.abs() > i64::MAX as f64 {
+                return None;
+            }
+            let a = a as i64;
+            (h, h1) = (a.checked_mul(h)?.checked_add(h1)?, h);
+            (k, k1) = (a.checked_mul(k)?.checked_add(k1)?, k);
+            if (h as f64 / k as f64 - x).abs() <= tolerance {
+                return Number::rational(h, k).ok();
+            }
+            y = 1.0 / (y - a as f64);
+        }
+        None
+    }
+
+    /// Closest fraction to `f` that converts back to exactly `f`, if any fits.
+    fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() {
+            return None;
+        }
+        let (mut h, mut h1, mut k, mut k1) = (1i128, 0i128, 0i128, 1i128);
+        let mut x = f;
+        for _ in 0..64 {
+            let a = x.floor();
+            if a.abs() > i64::MAX as f64 {
+                return None;
+            }
+            let a = a as i128;
+            (h, h1) = (a.checked_mul(h)?.checked_add(h1)?, h);
+            (k, k1) = (a.checked_mul(k)?.checked_add(k1)?, k);
+            if h.abs() > i64::MAX as i128 || k > i64::MAX as i128 {
+                return None;
+            }
+            if h as f64 / k as f64 == f {
+                return Number::from_ratio(h, k).ok();
+            }
+            x = 1.0 / (x - a as f64);
+            if !x.is_finite() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Converts a literal, or a value from another mode, for `mode`.
+    pub fn in_mode(self, mode: Mode) -> Result<Self, SyaError> {
+        match (mode, self) {
+            (Mode::Int, Number::Float(f)) if f.fract() == 0.0 && f.abs() <= i64::MAX as f64 => {
+                Ok(Number::Integer(f as i64))
+            }
+            (Mode::Int, n @ (Number::Float(_) | Number::Rational(..))) => {
+                Err(SyaError::NotInteger(n.to_string()))
+            }
+            (Mode::Float, n) => Ok(Number::Float(n.as_f64())),
+            (Mode::Exact, Number::Float(f)) => Ok(Number::from_f64(f).unwrap_or(Number::Float(f))),
+            (_, n) => Ok(n),
+        }
+    }
+
+    /// A result calculated exactly, as `mode` would have it: fractions are
+    /// floats outside exact mode.
+    pub fn settle(self, mode: Mode) -> Result<Self, SyaError> {
+        match (mode, self) {
+            (Mode::Auto, n @ Number::Rational(..)) => Ok(Number::Float(n.as_f64())),
+            (_, n) => n.in_mode(mode),
+        }
+    }
+
+    /// Applies an operator following the rules of `mode`.
+    pub fn checked_op_in(self, sign: char, other: Self, mode: Mode) -> Result<Self, SyaError> {
+        let (a, b) = match mode {
+            Mode::Int | Mode::Float => (self.in_mode(mode)?, other.in_mode(mode)?),
+            _ => (self, other),
+        };
+        match (mode, sign, a, b) {
+            (Mode::Int, '/', Number::Integer(a), Number::Integer(b)) => match a.checked_div(b) {
+                Some(q) => Ok(Number::Integer(q)),
+                None if b == 0 => Err(SyaError::DivisionByZero),
+                None => Err(SyaError::NumberOverflow(format!("{} / {}", a, b))),
+            },
+            (Mode::Int, '/', a @ Number::Big(_), b) | (Mode::Int, '/', a, b @ Number::Big(_)) => {
+                a.big_op('/', &b, true)
+            }
+            (Mode::Exact, '/', a, b) if a.ratio().is_some() && b.ratio().is_some() => {
+                a.ratio_op('/', &b)
+            }
+            // The reciprocal is a fraction in exact mode, and truncated in
+            // integer mode.
+            (Mode::Exact | Mode::Int, '^', a, Number::Integer(e)) if e < 0 && e > i64::MIN => {
+                let power = a.checked_op('^', Number::Integer(-e))?;
+                Number::Integer(1).checked_op_in('/', power, mode)
+            }
+            (_, _, a, b) => a.checked_op(sign, b),
+        }
+    }
+
+    /// The multiple of `step` that `self` rounds to. Floats are rounded as
+    /// the decimal they print as, so 2.675 is a tie, and stay floats.
+    pub fn round_to(self, step: Number, rounding: Rounding) -> Result<Self, SyaError> {
+        let float = matches!(self, Number::Float(_)) || matches!(step, Number::Float(_));
+        // Big integers are already multiples of steps like 1/100.
+        if matches!(self, Number::Big(_)) && matches!(step.ratio(), Some((1, _))) {
+            return Ok(self);
+        }
+        let exact = |n: &Number| match *n {
+            Number::Float(f) => Number::from_f64(f),
+            Number::Big(_) => None,
+            ref n => Some(n.clone()),
+        };
+        let ((n, d), (step_n, step_d)) = match (exact(&self).as_ref(), exact(&step).as_ref()) {
+            (Some(x), Some(s)) => (x.ratio().unwrap(), s.ratio().unwrap()),
+            // Too large or small for a fraction, at most the fraction is lost.
+            _ => {
+                let (x, step) = (self.as_f64(), step.as_f64());
+                return match step {
+                    0.0 => Err(SyaError::DivisionByZero),
+                    _ => Ok(Number::Float(rounding.float(x / step) * step)),
+                };
+            }
+        };
+        if step_n == 0 {
+            return Err(SyaError::DivisionByZero);
+        }
+        let (n, d) = (n * step_d * step_n.signum(), d * step_n.abs());
+        let q = rounding.quotient(n, d);
+        let rounded = Number::from_ratio(q * step_n, step_d)?;
+        match float {
+            true => Ok(Number::Float(rounded.as_f64())),
+            false => Ok(rounded),
+        }
+    }
+
+    /// Rounds floats to `digits` after the decimal point.
+    pub fn round_digits(self, digits: usize) -> Self {
         match self {
+            Number::Float(f) => {
+                let scale = 10f64.powi(digits.min(308) as i32);
+                let rounded = (f * scale).round() / scale;
+                // Scaling huge numbers overflows, and they have no decimals.
+                Number::Float(if rounded.is_finite() { rounded } else { f })
+            }
+            n => n,
+        }
+    }
+
+    pub fn as_u32(&self) -> Result<u32, SyaError> {
+        match *self {
             Number::Integer(i) => i
                 .try_into()
                 .map_err(|_| SyaError::NumberOverflow(i.to_string())),
             Number::Float(f) => Err(SyaError::Custom(format!("Unsafe operation {} as u32", f))), // Floats can't be directly converted to u32 safely
+            Number::Rational(..) => Err(SyaError::Custom(format!(
+                "Unsafe operation {} as u32",
+                self
+            ))),
+            Number::Big(ref b) => Err(SyaError::NumberOverflow(b.to_string())),
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Integer(i) => i as f64,
+            Number::Float(f) => f,
+            Number::Rational(n, d) => n as f64 / d as f64,
+            Number::Big(ref b) => b.to_f64(),
+        }
+    }
+
+    pub fn checked_op(self, sign: char, other: Self) -> Result<Self, SyaError> {
+        match sign {
+            '+' => self.checked_add(other),
+            '-' => self.checked_sub(other),
+            '*' => self.checked_mul(other),
+            '/' => self.checked_div(other),
+            '^' => self.checked_power(other),
+            _ => Err(SyaError::InvalidOperation(sign)),
         }
     }
 
@@ -28,33 +450,47 @@ impl Number {
         match self {
             Number::Integer(i) => Number::Integer(-i),
             Number::Float(f) => Number::Float(-f),
+            Number::Rational(n, d) => Number::Rational(-n, d),
+            Number::Big(b) => Number::big(b.negate()),
         }
     }
 
     pub fn checked_add(self, other: Self) -> Result<Self, SyaError> {
         match (self, other) {
-            (Number::Integer(a), Number::Integer(b)) => Ok(Number::Integer(a + b)),
-            (Number::Float(a), Number::Float(b)) => Ok(Number::Float(a + b)),
-            (Number::Integer(a), Number::Float(b)) => Ok(Number::Float(a as f64 + b)),
-            (Number::Float(a), Number::Integer(b)) => Ok(Number::Float(a + b as f64)),
+            (Number::Integer(a), Number::Integer(b)) => match a.checked_add(b) {
+                Some(n) => Ok(Number::Integer(n)),
+                None => Err(SyaError::NumberOverflow(format!("{} + {}", a, b))),
+            },
+            (Number::Float(a), b) => Ok(Number::Float(a + b.as_f64())),
+            (a, Number::Float(b)) => Ok(Number::Float(a.as_f64() + b)),
+            (a @ Number::Big(_), b) | (a, b @ Number::Big(_)) => a.big_op('+', &b, false),
+            (a, b) => a.ratio_op('+', &b),
         }
     }
 
     pub fn checked_sub(self, other: Self) -> Result<Self, SyaError> {
         match (self, other) {
-            (Number::Integer(a), Number::Integer(b)) => Ok(Number::Integer(a - b)),
-            (Number::Float(a), Number::Float(b)) => Ok(Number::Float(a - b)),
-            (Number::Integer(a), Number::Float(b)) => Ok(Number::Float(a as f64 - b)),
-            (Number::Float(a), Number::Integer(b)) => Ok(Number::Float(a - b as f64)),
+            (Number::Integer(a), Number::Integer(b)) => match a.checked_sub(b) {
+                Some(n) => Ok(Number::Integer(n)),
+                None => Err(SyaError::NumberOverflow(format!("{} - {}", a, b))),
+            },
+            (Number::Float(a), b) => Ok(Number::Float(a - b.as_f64())),
+            (a, Number::Float(b)) => Ok(Number::Float(a.as_f64() - b)),
+            (a @ Number::Big(_), b) | (a, b @ Number::Big(_)) => a.big_op('-', &b, false),
+            (a, b) => a.ratio_op('-', &b),
         }
     }
 
     pub fn checked_mul(self, other: Self) -> Result<Self, SyaError> {
         match (self, other) {
-            (Number::Integer(a), Number::Integer(b)) => Ok(Number::Integer(a * b)),
-            (Number::Float(a), Number::Float(b)) => Ok(Number::Float(a * b)),
-            (Number::Integer(a), Number::Float(b)) => Ok(Number::Float(a as f64 * b)),
-            (Number::Float(a), Number::Integer(b)) => Ok(Number::Float(a * b as f64)),
+            (Number::Integer(a), Number::Integer(b)) => match a.checked_mul(b) {
+                Some(n) => Ok(Number::Integer(n)),
+                None => Err(SyaError::NumberOverflow(format!("{} * {}", a, b))),
+            },
+            (Number::Float(a), b) => Ok(Number::Float(a * b.as_f64())),
+            (a, Number::Float(b)) => Ok(Number::Float(a.as_f64() * b)),
+            (a @ Number::Big(_), b) | (a, b @ Number::Big(_)) => a.big_op('*', &b, false),
+            (a, b) => a.ratio_op('*', &b),
         }
     }
 
@@ -69,26 +505,44 @@ impl Number {
                     Ok(Number::Float(a as f64 / b as f64))
                 }
             }
-            (Number::Float(a), Number::Float(b)) => {
-                if b == 0.0 {
+            (Number::Float(a), b) => {
+                if b.as_f64() == 0.0 {
                     Err(SyaError::DivisionByZero)
                 } else {
-                    Ok(Number::Float(a / b))
+                    Ok(Number::Float(a / b.as_f64()))
                 }
             }
-            (Number::Integer(a), Number::Float(b)) => {
+            (a, Number::Float(b)) => {
                 if b == 0.0 {
                     Err(SyaError::DivisionByZero)
                 } else {
-                    Ok(Number::Float(a as f64 / b))
+                    Ok(Number::Float(a.as_f64() / b))
                 }
             }
-            (Number::Float(a), Number::Integer(b)) => {
-                if b == 0 {
-                    Err(SyaError::DivisionByZero)
-                } else {
-                    Ok(Number::Float(a / b as f64))
+            (a @ Number::Big(_), b) | (a, b @ Number::Big(_)) => a.big_op('/', &b, false),
+            (a, b) => a.ratio_op('/', &b),
+        }
+    }
+
+    /// `self` to any power: exact for whole exponents, with the reciprocal
+    /// for negative ones, and a float for the others.
+    pub fn checked_power(self, exp: Self) -> Result<Self, SyaError> {
+        match exp {
+            Number::Integer(e) if e < 0 => {
+                let magnitude = u32::try_from(e.unsigned_abs())
+                    .map_err(|_| SyaError::NumberOverflow(e.to_string()))?;
+                Number::Integer(1).checked_div(self.checked_pow(magnitude)?)
+            }
+            Number::Integer(_) => self.checked_pow(exp.as_u32()?),
+            exp => {
+                let (base, power) = (self.as_f64(), exp.as_f64());
+                if base < 0.0 && power.fract() != 0.0 {
+                    return Err(SyaError::OutOfDomain(
+                        "^".to_string(),
+                        format!("{}, {}", self, exp),
+                    ));
                 }
+                Ok(Number::Float(base.powf(power)))
             }
         }
     }
@@ -103,6 +557,21 @@ impl Number {
                 Ok(Number::Integer(p))
             }
             Number::Float(a) => Ok(Number::Float(a.powi(exp as i32))),
+            Number::Rational(n, d) => match (n.checked_pow(exp), d.checked_pow(exp)) {
+                (Some(n), Some(d)) => Number::rational(n, d),
+                _ => Err(SyaError::NumberOverflow(exp.to_string())),
+            },
+            Number::Big(b) if b.bits().saturating_mul(exp as u64) > MAX_BITS => {
+                Err(SyaError::NumberOverflow(format!("{} ^ {}", b, exp)))
+            }
+            Number::Big(b) => Ok(Number::big(b.pow(exp))),
         }
     }
 }
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
@@ -0,0 +1,80 @@
+use crate::{
+    expr::Expr,
+    number::{Mode, Number},
+};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SimplifyOptions {
+    /// Also applies identities such as `x * 0 = 0` and `x - x = 0`, which
+    /// don't hold when `x` is NaN or can't be evaluated, and `x ^ 0 = 1`,
+    /// which turns a float `x` into an integer result.
+    pub aggressive: bool,
+    /// Mode constant parts are folded in, the same as evaluation.
+    pub mode: Mode,
+}
+
+/// Folds constant subexpressions and removes identity operations.
+/// Subexpressions that fail to fold, like `1 / 0`, are kept as they are so
+/// the error is reported on evaluation.
+pub fn simplify(expr: Expr, options: &SimplifyOptions) -> Expr {
+    match expr {
+        Expr::Unary(s, e) => simplify_unary(s, simplify(*e, options)),
+        Expr::Binary(sign, a, b) => {
+            simplify_binary(sign, simplify(*a, options), simplify(*b, options), options)
+        }
+        Expr::Call(name, args) => Expr::Call(
+            name,
+            args.into_iter().map(|a| simplify(a, options)).collect(),
+        ),
+        Expr::Quantity(e, unit) => Expr::Quantity(Box::new(simplify(*e, options)), unit),
+        Expr::Convert(e, unit) => Expr::Convert(Box::new(simplify(*e, options)), unit),
+        e => e,
+    }
+}
+
+fn simplify_unary(sign: char, e: Expr) -> Expr {
+    match (sign, e) {
+        ('+', e) => e,
+        ('-', Expr::Number(n)) => Expr::Number(n.negate()),
+        ('-', Expr::Unary('-', e)) => *e,
+        (s, e) => Expr::Unary(s, Box::new(e)),
+    }
+}
+
+fn simplify_binary(sign: char, a: Expr, b: Expr, options: &SimplifyOptions) -> Expr {
+    if let (Expr::Number(x), Expr::Number(y)) = (&a, &b) {
+        let mode = options.mode;
+        let folded = x.clone().in_mode(mode).and_then(|x| {
+            let y = y.clone().in_mode(mode)?;
+            x.checked_op_in(sign, y, mode)
+        });
+        if let Ok(n) = folded {
+            return Expr::Number(n);
+        }
+    }
+
+    match (sign, a, b) {
+        ('*', Expr::Number(Number::Integer(x)), Expr::Binary('*', c, e))
+        | ('*', Expr::Binary('*', c, e), Expr::Number(Number::Integer(x)))
+            if matches!(*c, Expr::Number(Number::Integer(_))) =>
+        {
+            let c = simplify_binary('*', Expr::Number(Number::Integer(x)), *c, options);
+            simplify_binary('*', c, *e, options)
+        }
+        ('+', e, z) | ('+', z, e) | ('-', e, z) if is(&z, 0) => e,
+        ('-', z, e) if is(&z, 0) => simplify_unary('-', e),
+        ('*', e, o) | ('*', o, e) | ('/', e, o) | ('^', e, o) if is(&o, 1) => e,
+        ('*', _, z) | ('*', z, _) if options.aggressive && is(&z, 0) => z,
+        ('/', z, _) if options.aggressive && is(&z, 0) => z,
+        ('-', a, b) if options.aggressive && a == b => Expr::Number(Number::Integer(0)),
+        ('/', a, b) if options.aggressive && a == b => Expr::Number(Number::Integer(1)),
+        ('^', _, z) if options.aggressive && is(&z, 0) => Expr::Number(Number::Integer(1)),
+        (s, a, b) => Expr::Binary(s, Box::new(a), Box::new(b)),
+    }
+}
+
+/// Only integer literals are identities, `x * 1.0` would turn an integer
+/// `x` into a float.
+fn is(e: &Expr, n: i64) -> bool {
+    *e == Expr::Number(Number::Integer(n))
+}
@@ -0,0 +1,127 @@
+use crate::errors::SyaError;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SolverOptions {
+    pub tolerance: f64,
+    pub max_iterations: usize,
+}
+impl Default for SolverOptions {
+    fn default() -> Self {
+        SolverOptions {
+            tolerance: 1e-12,
+            max_iterations: 100,
+        }
+    }
+}
+impl SolverOptions {
+    /// A tolerance, relative to the size of the root, that's positive.
+    pub fn parse_tolerance(s: &str) -> Option<f64> {
+        s.parse().ok().filter(|t: &f64| *t > 0.0 && t.is_finite())
+    }
+
+    pub fn parse_iterations(s: &str) -> Option<usize> {
+        s.parse().ok().filter(|n| *n > 0)
+    }
+}
+
+/// Newton's method starting at `x0`, `df` is the derivative of `f`.
+pub fn newton<F, D>(f: F, df: D, x0: f64, options: &SolverOptions) -> Result<f64, SyaError>
+where
+    F: Fn(f64) -> Result<f64, SyaError>,
+    D: Fn(f64) -> Result<f64, SyaError>,
+{
+    let mut x = x0;
+    for _ in 0..options.max_iterations {
+        let fx = f(x)?;
+        if fx == 0.0 {
+            return Ok(x);
+        }
+        let slope = df(x)?;
+        if slope == 0.0 || !slope.is_finite() {
+            break;
+        }
+
+        let step = fx / slope;
+        x -= step;
+        if !x.is_finite() {
+            break;
+        }
+        if step.abs() <= options.tolerance * (1.0 + x.abs()) {
+            return Ok(x);
+        }
+    }
+    Err(SyaError::NoConvergence(options.max_iterations))
+}
+
+/// Brent's method over `[a, b]`, combining bisection, the secant method and
+/// inverse quadratic interpolation. `f(a)` and `f(b)` must differ in sign.
+/// Like Newton's method it stops within a tolerance relative to the root,
+/// since large roots can't be pinned down any closer than their float
+/// spacing.
+pub fn brent<F>(f: F, a: f64, b: f64, options: &SolverOptions) -> Result<f64, SyaError>
+where
+    F: Fn(f64) -> Result<f64, SyaError>,
+{
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (f(a)?, f(b)?);
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fb == 0.0 {
+        return Ok(b);
+    }
+    if fa.signum() == fb.signum() {
+        return Err(SyaError::NotBracketed(a, b));
+    }
+    if fa.abs() < fb.abs() {
+        std::mem::swap(&mut a, &mut b);
+        std::mem::swap(&mut fa, &mut fb);
+    }
+
+    let (mut c, mut fc) = (a, fa);
+    let mut d = c;
+    let mut bisected = true;
+    for _ in 0..options.max_iterations {
+        let tolerance = options.tolerance * (1.0 + b.abs());
+        if fb == 0.0 || (b - a).abs() <= tolerance {
+            return Ok(b);
+        }
+
+        let mut s = if fa != fc && fb != fc {
+            a * fb * fc / ((fa - fb) * (fa - fc))
+                + b * fa * fc / ((fb - fa) * (fb - fc))
+                + c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            b - fb * (b - a) / (fb - fa)
+        };
+
+        let quarter = (3.0 * a + b) / 4.0;
+        let outside = !((s > quarter.min(b)) && (s < quarter.max(b)));
+        let slow = if bisected {
+            (s - b).abs() >= (b - c).abs() / 2.0 || (b - c).abs() < tolerance
+        } else {
+            (s - b).abs() >= (c - d).abs() / 2.0 || (c - d).abs() < tolerance
+        };
+        bisected = outside || slow;
+        if bisected {
+            s = (a + b) / 2.0;
+        }
+
+        let fs = f(s)?;
+        d = c;
+        c = b;
+        fc = fb;
+        if fa.signum() != fs.signum() {
+            b = s;
+            fb = fs;
+        } else {
+            a = s;
+            fa = fs;
+        }
+        if fa.abs() < fb.abs() {
+            std::mem::swap(&mut a, &mut b);
+            std::mem::swap(&mut fa, &mut fb);
+        }
+    }
+    Err(SyaError::NoConvergence(options.max_iterations))
+}
@@ -0,0 +1,143 @@
+use crate::{
+    errors::SyaError,
+    functions,
+    number::{Angle, Mode, Number},
+};
+
+/// Calls the statistical function `name` on any number of values, or returns
+/// `None` when `name` isn't one. Divisions follow `mode`, so results stay
+/// integers while they are exact.
+pub fn call(name: &str, args: &[Number], mode: Mode) -> Option<Result<Number, SyaError>> {
+    let result = match name {
+        "sum" => sum(name, args),
+        "mean" => mean(name, args, mode),
+        "median" => median(name, args, mode),
+        "mode" => most_common(name, args),
+        "min" => sorted(name, args).map(|s| s[0].clone()),
+        "max" => sorted(name, args).map(|s| s[s.len() - 1].clone()),
+        "var" => variance(name, args, 1, mode),
+        "varp" => variance(name, args, 0, mode),
+        "stdev" => variance(name, args, 1, mode).and_then(sqrt),
+        "stdevp" => variance(name, args, 0, mode).and_then(sqrt),
+        "percentile" => match args.split_first() {
+            Some((p, values)) => percentile(name, p, values, mode),
+            None => Err(SyaError::InvalidArguments(name.to_string())),
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Whether `name` is a statistical function, which takes the elements of
+/// lists as values.
+pub fn is_statistic(name: &str) -> bool {
+    matches!(
+        name,
+        "sum"
+            | "mean"
+            | "median"
+            | "mode"
+            | "min"
+            | "max"
+            | "var"
+            | "varp"
+            | "stdev"
+            | "stdevp"
+            | "percentile"
+    )
+}
+
+fn sum(name: &str, args: &[Number]) -> Result<Number, SyaError> {
+    let (first, rest) = args
+        .split_first()
+        .ok_or_else(|| SyaError::InvalidArguments(name.to_string()))?;
+    rest.iter()
+        .try_fold(first.clone(), |total, n| total.checked_add(n.clone()))
+}
+
+fn mean(name: &str, args: &[Number], mode: Mode) -> Result<Number, SyaError> {
+    let count = Number::Integer(args.len() as i64);
+    sum(name, args)?.checked_op_in('/', count, mode)
+}
+
+/// The values from smallest to largest, NaN last.
+fn sorted(name: &str, args: &[Number]) -> Result<Vec<Number>, SyaError> {
+    if args.is_empty() {
+        return Err(SyaError::InvalidArguments(name.to_string()));
+    }
+    let mut values = args.to_vec();
+    values.sort_by(|a, b| a.as_f64().total_cmp(&b.as_f64()));
+    Ok(values)
+}
+
+fn median(name: &str, args: &[Number], mode: Mode) -> Result<Number, SyaError> {
+    let values = sorted(name, args)?;
+    let mid = values.len() / 2;
+    match values.len() % 2 {
+        1 => Ok(values[mid].clone()),
+        _ => mean(name, &values[mid - 1..=mid], mode),
+    }
+}
+
+/// The most frequent value, the smallest one on ties.
+fn most_common(name: &str, args: &[Number]) -> Result<Number, SyaError> {
+    let values = sorted(name, args)?;
+    let mut best = (&values[0], 0);
+    for run in values.chunk_by(|a, b| a.as_f64() == b.as_f64()) {
+        if run.len() > best.1 {
+            best = (&run[0], run.len());
+        }
+    }
+    Ok(best.0.clone())
+}
+
+/// Variance, dividing the squared deviations by the count minus
+/// `correction`: 1 for a sample and 0 for a whole population.
+fn variance(
+    name: &str,
+    args: &[Number],
+    correction: usize,
+    mode: Mode,
+) -> Result<Number, SyaError> {
+    if args.len() <= correction {
+        return Err(SyaError::InvalidArguments(name.to_string()));
+    }
+    let mean = mean(name, args, mode)?;
+    let squares = args
+        .iter()
+        .map(|n| {
+            let deviation = n.clone().checked_op_in('-', mean.clone(), mode)?;
+            deviation.clone().checked_op_in('*', deviation, mode)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = Number::Integer((args.len() - correction) as i64);
+    sum(name, &squares)?.checked_op_in('/', count, mode)
+}
+
+fn sqrt(n: Number) -> Result<Number, SyaError> {
+    functions::call("sqrt", &[n], Angle::Rad)
+}
+
+/// The `p`th percentile, from 0 to 100, interpolating linearly between the
+/// closest ranks. Ranks are exact so whole results stay integers.
+fn percentile(name: &str, p: &Number, args: &[Number], mode: Mode) -> Result<Number, SyaError> {
+    if !(0.0..=100.0).contains(&p.as_f64()) {
+        return Err(SyaError::OutOfDomain(name.to_string(), p.to_string()));
+    }
+    let values = sorted(name, args)?;
+    let exact = |a: Number, sign: char, b: Number| a.checked_op_in(sign, b, Mode::Exact);
+    let last = Number::Integer(values.len() as i64 - 1);
+    let rank = exact(exact(p.clone(), '*', last)?, '/', Number::Integer(100))?;
+    let below = rank.as_f64().floor() as usize;
+    let fraction = exact(rank, '-', Number::Integer(below as i64))?;
+    if fraction.as_f64() == 0.0 {
+        return Ok(values[below].clone());
+    }
+    let (low, high) = (values[below].clone(), values[below + 1].clone());
+    let step = exact(high, '-', low.clone())?;
+    match (mode, exact(low, '+', exact(step, '*', fraction)?)?) {
+        (Mode::Exact, n) => Ok(n),
+        (_, n @ Number::Rational(..)) => Ok(Number::Float(n.as_f64())),
+        (_, n) => Ok(n),
+    }
+}
@@ -1,49 +1,305 @@
-use crate::{errors::SyaError, number::Number};
+use std::{cell::Cell, collections::HashMap};
+
+use crate::{
+    constants, dates,
+    diff::diff,
+    errors::SyaError,
+    expr::Expr,
+    functions::{self, Function, FUNCTIONS},
+    integers, lists, matrix,
+    number::{Angle, Mode, Number, Rounding},
+    simplify::{simplify, SimplifyOptions},
+    solve::{brent, newton, SolverOptions},
+    stats,
+    trace::{EvalStep, RpnStep, Trace},
+    units::Units,
+    value::Value,
+};
 
 use super::tokenizer::{Token, Tokenizer};
 
+/// Calls of user functions that can be nested, recursion has no base case.
+const MAX_DEPTH: usize = 256;
+
 #[derive(Debug)]
 pub struct Sya {
+    pub statements: Vec<Vec<Token>>,
+    /// Character range of each statement in the input.
+    spans: Vec<(usize, usize)>,
     pub input: Vec<Token>,
     pub rpn_stack: Vec<Token>,
-    pub out: Option<Number>,
+    pub out: Option<Value>,
+    pub vars: HashMap<String, Value>,
+    pub functions: HashMap<String, Function>,
+    /// Result of every calculated input, `ans` is the last one and `$n` the
+    /// nth one.
+    pub results: Vec<Value>,
+    pub mode: Mode,
+    pub angle: Angle,
+    pub rounding: Rounding,
+    /// Digits constants are accurate to in exact mode. Unset, they are the
+    /// fraction closest to their float.
+    pub precision: Option<usize>,
+    pub units: Units,
+    pub trace: Option<Trace>,
+    /// Simplifies each statement before evaluating it when set.
+    pub simplify: Option<SimplifyOptions>,
+    pub solver: SolverOptions,
+    /// Character range of the input that caused the last error.
+    pub error_span: Option<(usize, usize)>,
+    /// User functions being called.
+    depth: Cell<usize>,
 }
 impl Sya {
     pub fn new(input: &str) -> Result<Sya, SyaError> {
         let mut s = Sya {
+            statements: Vec::new(),
+            spans: Vec::new(),
             input: Vec::new(),
             rpn_stack: Vec::new(),
             out: None,
+            vars: HashMap::new(),
+            functions: HashMap::new(),
+            results: Vec::new(),
+            mode: Mode::default(),
+            angle: Angle::default(),
+            rounding: Rounding::default(),
+            precision: None,
+            units: Units::default(),
+            trace: None,
+            simplify: None,
+            solver: SolverOptions::default(),
+            error_span: None,
+            depth: Cell::new(0),
         };
         s.new_input(input)?;
         Ok(s)
     }
 
     pub fn new_input(&mut self, input: &str) -> Result<(), SyaError> {
-        let mut tokenizer = Tokenizer::new(input);
-        let tokens = tokenizer.parse()?;
-        self.input = tokens.clone();
+        let sources = split_statements(input);
+        let many = sources.len() > 1;
+        self.input.clear();
         self.rpn_stack.clear();
         self.out = None;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+        self.statements.clear();
+        self.spans.clear();
+        self.error_span = None;
+        let mut offset = 0;
+        for (i, source) in sources.iter().enumerate() {
+            let mut tokenizer = Tokenizer::new(source).with_units(&self.units);
+            let tokens = match tokenizer.parse() {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    // Kept so the tokens read before the error can be shown.
+                    self.statements
+                        .push(tokenizer.spanned().map(|(t, _)| t.clone()).collect());
+                    let (start, end) = tokenizer.span();
+                    self.error_span = Some((offset + start, offset + end));
+                    return Err(in_statement(many, i, e));
+                }
+            };
+            self.statements.push(tokens.clone());
+
+            let len = source.chars().count();
+            let leading = len - source.trim_start().chars().count();
+            let trailing = len - source.trim_end().chars().count();
+            self.spans.push((
+                offset + leading,
+                (offset + len - trailing).max(offset + leading),
+            ));
+            offset += len + 1;
+        }
         Ok(())
     }
 
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.trace = if enabled {
+            Some(Trace::default())
+        } else {
+            None
+        };
+    }
+
+    /// Evaluates every statement in order, assigning `name = ...` statements
+    /// to `vars`. `out` holds the value of the last statement.
     pub fn calculate(&mut self) -> Result<(), SyaError> {
+        self.out = None;
+        self.error_span = None;
+        let many = self.statements.len() > 1;
+        for (i, tokens) in self.statements.clone().into_iter().enumerate() {
+            if many && tokens.is_empty() {
+                continue;
+            }
+            if let Some(trace) = self.trace.as_mut() {
+                trace.begin();
+            }
+            let (target, tokens) = split_assignment(tokens);
+            self.input = tokens;
+            self.rpn_stack.clear();
+            let value = match &target {
+                Some(Target::Function(name, params)) => {
+                    self.define_function(name.clone(), params.clone())
+                }
+                _ => self.evaluate(),
+            };
+            let value = value.map_err(|e| {
+                self.error_span = self.spans.get(i).copied();
+                in_statement(many, i, e)
+            })?;
+            if let Some(Target::Var(name)) = target {
+                if name == "ans" || name.starts_with('$') || constants::get(&name).is_some() {
+                    self.error_span = self.spans.get(i).copied();
+                    return Err(in_statement(many, i, SyaError::ReadOnly(name)));
+                }
+                self.vars.insert(name, value.clone());
+            }
+            self.out = Some(value);
+        }
+
+        match &self.out {
+            Some(out) => {
+                self.results.push(out.clone());
+                Ok(())
+            }
+            None => Err(SyaError::InvalidInput),
+        }
+    }
+
+    /// Runs `input` for its definitions, without keeping it as a result.
+    pub fn define(&mut self, input: &str) -> Result<(), SyaError> {
+        self.new_input(input)?;
+        self.calculate()?;
+        self.results.pop();
+        Ok(())
+    }
+
+    /// Stores the statement being calculated as the body of a function, and
+    /// returns the body.
+    fn define_function(&mut self, name: String, params: Vec<String>) -> Result<Value, SyaError> {
+        if FUNCTIONS.iter().any(|(builtin, _)| *builtin == name) {
+            return Err(SyaError::ReadOnly(name));
+        }
+        self.rpn()?;
+        let body = Expr::from_rpn(&self.rpn_stack)?;
+        let function = Function { name, params, body };
+        self.functions
+            .insert(function.name.clone(), function.clone());
+        Ok(Value::Expr(function.body))
+    }
+
+    /// Resolves a constant, a variable, `ans` or a `$n` reference to a
+    /// previous result.
+    pub fn lookup(&self, name: &str) -> Result<Value, SyaError> {
+        if let Some(c) = constants::get(name) {
+            let exact = match (self.mode, self.precision) {
+                (Mode::Exact, Some(digits)) => {
+                    Number::approximate(c, 0.5 * 10f64.powi(-(digits.min(300) as i32)))
+                }
+                _ => None,
+            };
+            return match exact {
+                Some(n) => Ok(Value::Number(n)),
+                None => Ok(Value::Number(Number::Float(c).in_mode(self.mode)?)),
+            };
+        }
+        if let Some(v) = self.vars.get(name) {
+            return Ok(v.clone());
+        }
+        let result = match name {
+            "ans" => self.results.last(),
+            _ => match name.strip_prefix('$') {
+                Some(n) => n
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| self.results.get(n)),
+                None => return Err(SyaError::UnknownVariable(name.to_string())),
+            },
+        };
+        result
+            .cloned()
+            .ok_or_else(|| SyaError::UnknownResult(name.to_string()))
+    }
+
+    /// Returns the simplified infix form of every statement, without
+    /// evaluating them.
+    pub fn simplified(&mut self, options: &SimplifyOptions) -> Result<String, SyaError> {
+        let many = self.statements.len() > 1;
+        let mut out = Vec::new();
+        for (i, tokens) in self.statements.clone().into_iter().enumerate() {
+            if many && tokens.is_empty() {
+                continue;
+            }
+            if let Some(trace) = self.trace.as_mut() {
+                trace.begin();
+            }
+            let (target, tokens) = split_assignment(tokens);
+            self.input = tokens;
+            self.rpn_stack.clear();
+            let expr = self
+                .rpn()
+                .and_then(|_| Expr::from_rpn(&self.rpn_stack))
+                .map_err(|e| in_statement(many, i, e))?;
+            let expr = simplify(expr, options);
+            out.push(match target {
+                Some(Target::Var(name)) => format!("{} = {}", name, expr),
+                Some(Target::Function(name, params)) => {
+                    format!("{}({}) = {}", name, params.join(", "), expr)
+                }
+                None => expr.to_string(),
+            });
+        }
+        Ok(out.join("; "))
+    }
+
+    fn evaluate(&mut self) -> Result<Value, SyaError> {
         self.rpn()?;
-        let mut operation_stack = Vec::new();
-        for token in &self.rpn_stack {
+        if let Some(options) = &self.simplify {
+            let options = SimplifyOptions {
+                mode: self.mode,
+                ..options.clone()
+            };
+            let expr = Expr::from_rpn(&self.rpn_stack)?;
+            self.rpn_stack = simplify(expr, &options).to_rpn();
+        }
+        let mut trace = self.trace.take();
+        let result = self.run(&self.rpn_stack, trace.as_mut().map(|t| &mut t.eval));
+        self.trace = trace;
+        // Units asked for with `to` are kept.
+        match self.rpn_stack.last() {
+            Some(Token::Convert(_)) => result,
+            _ => self.units.readable(result?, self.mode),
+        }
+    }
+
+    fn run(&self, rpn: &[Token], mut steps: Option<&mut Vec<EvalStep>>) -> Result<Value, SyaError> {
+        let quoted = quoted_ranges(rpn);
+        let mut operation_stack: Vec<Value> = Vec::new();
+        let mut i = 0;
+        while i < rpn.len() {
+            let before = steps.as_ref().map(|_| operation_stack.clone());
+            // Special forms get their arguments as expressions, so those are
+            // skipped instead of evaluated.
+            if let Some(&end) = quoted.get(&i) {
+                i = end;
+            }
+            let token = &rpn[i];
             match token {
-                Token::Number(i) => operation_stack.push(i.clone()),
+                Token::Number(n) => {
+                    operation_stack.push(Value::Number(n.clone().in_mode(self.mode)?))
+                }
+                Token::Date(d) => operation_stack.push(Value::Date(d.clone())),
+                Token::Ident(name) => operation_stack.push(self.lookup(name)?),
                 Token::UNARY(s) => {
                     let n = match operation_stack.pop() {
                         Some(n) => n,
                         None => return Err(SyaError::ExpectedStackSize(1)),
                     };
-                    match s {
-                        '-' => operation_stack.push(n.negate()),
-                        '+' => operation_stack.push(n),
-                        _ => return Err(SyaError::WrongUnary(s.clone())),
-                    };
+                    operation_stack.push(n.unary(*s)?);
                 }
 
                 Token::Operator(o) => {
@@ -54,47 +310,394 @@ impl Sya {
                     let b = operation_stack.pop().unwrap();
                     let a = operation_stack.pop().unwrap();
 
-                    let result = match o.sign {
-                        '+' => a.checked_add(b),
-                        '-' => a.checked_sub(b),
-                        '*' => a.checked_mul(b),
-                        '/' => a.checked_div(b),
-                        '^' => a.checked_pow(b.as_u32()?),
-                        _ => Err(SyaError::InvalidOperation(o.sign)),
-                    };
-
-                    operation_stack.push(result?);
+                    operation_stack.push(a.binary(o.sign, b, self.mode)?);
+                }
+                Token::Unit(unit) => {
+                    let v = operation_stack
+                        .pop()
+                        .ok_or(SyaError::ExpectedStackSize(1))?;
+                    operation_stack.push(match v {
+                        Value::Expr(e) => Value::Expr(Expr::Quantity(Box::new(e), unit.clone())),
+                        v => v.binary(
+                            '*',
+                            Value::Quantity(Box::new(self.units.one(unit)?)),
+                            self.mode,
+                        )?,
+                    });
+                }
+                Token::Convert(unit) => {
+                    let v = operation_stack
+                        .pop()
+                        .ok_or(SyaError::ExpectedStackSize(1))?;
+                    operation_stack.push(self.units.convert(v, unit, self.mode)?);
+                }
+                Token::Call(name, argc) if is_special(name) => {
+                    let start = operand_start(rpn, i, *argc);
+                    let args = Expr::from_rpn_many(&rpn[start..i])?;
+                    operation_stack.push(self.special_form(name, args)?);
+                }
+                Token::Call(name, argc) => {
+                    if operation_stack.len() < *argc {
+                        return Err(SyaError::ExpectedStackSize(*argc as u32));
+                    }
+                    let args = operation_stack.split_off(operation_stack.len() - argc);
+                    operation_stack.push(self.call(name, args)?);
                 }
                 _ => return Err(SyaError::InvalidToken(token.clone())),
             }
+            if let (Some(steps), Some(before)) = (steps.as_mut(), before) {
+                steps.push(EvalStep {
+                    token: token.clone(),
+                    before,
+                    after: operation_stack.clone(),
+                });
+            }
+            i += 1;
         }
 
         if operation_stack.len() != 1 {
             return Err(SyaError::InvalidInput);
         }
-        let last = operation_stack.last().unwrap();
-        self.out = Some(last.clone());
-        Ok(())
+        Ok(operation_stack.pop().unwrap())
+    }
+
+    fn call(&self, name: &str, args: Vec<Value>) -> Result<Value, SyaError> {
+        let function = match self.functions.get(name) {
+            Some(function) => function,
+            None => return self.call_builtin(name, args),
+        };
+        if args.len() != function.params.len() {
+            return Err(SyaError::InvalidArguments(name.to_string()));
+        }
+        let depth = self.depth.get();
+        if depth >= MAX_DEPTH {
+            return Err(SyaError::RecursionLimit(name.to_string()));
+        }
+
+        let bound: Vec<(String, Expr)> = function
+            .params
+            .iter()
+            .cloned()
+            .zip(args.into_iter().map(Value::into_expr))
+            .collect();
+        self.depth.set(depth + 1);
+        let result = self.run(&function.body.replace_all(&bound).to_rpn(), None);
+        self.depth.set(depth);
+        result
+    }
+
+    /// Calls a built-in function, staying symbolic when an argument is.
+    /// Functions of numbers apply to each element of list arguments, and
+    /// statistics to every element.
+    fn call_builtin(&self, name: &str, args: Vec<Value>) -> Result<Value, SyaError> {
+        let args = match stats::is_statistic(name) {
+            true => flatten(args),
+            false => args,
+        };
+        if name != "list" && args.iter().any(|a| matches!(a, Value::Expr(_))) {
+            let args = args.into_iter().map(Value::into_expr).collect();
+            return Ok(Value::Expr(Expr::Call(name.to_string(), args)));
+        }
+        if let Some(result) = lists::call(name, &args, self.mode)
+            .or_else(|| matrix::call(name, &args, self.mode))
+            .or_else(|| dates::call(name, &args))
+        {
+            return result;
+        }
+        if args.iter().any(|a| matches!(a, Value::List(_))) {
+            return self.broadcast(name, args);
+        }
+        let mut numbers = Vec::new();
+        for arg in args {
+            match arg {
+                Value::Number(n) => numbers.push(n),
+                v => return Err(SyaError::ExpectedNumber(v.to_string())),
+            }
+        }
+        if name == "factor" {
+            return match integers::factor(&numbers)? {
+                Expr::Number(n) => Ok(Value::Number(n)),
+                product => Ok(Value::Expr(product)),
+            };
+        }
+        let result = stats::call(name, &numbers, self.mode)
+            .or_else(|| integers::call(name, &numbers))
+            .or_else(|| functions::round(name, &numbers, self.rounding))
+            .unwrap_or_else(|| functions::call(name, &numbers, self.angle))?;
+        match self.mode {
+            Mode::Int | Mode::Float => Ok(Value::Number(result.in_mode(self.mode)?)),
+            _ => Ok(Value::Number(result)),
+        }
+    }
+
+    /// Calls `name` once for each element of the list arguments, which must
+    /// have the same length, with the other arguments as they are.
+    fn broadcast(&self, name: &str, args: Vec<Value>) -> Result<Value, SyaError> {
+        let lengths: Vec<usize> = args
+            .iter()
+            .filter_map(|a| match a {
+                Value::List(items) => Some(items.len()),
+                _ => None,
+            })
+            .collect();
+        if let Some(&other) = lengths.iter().find(|&&len| len != lengths[0]) {
+            return Err(SyaError::LengthMismatch(lengths[0], other));
+        }
+        (0..lengths[0])
+            .map(|i| {
+                let args = args
+                    .iter()
+                    .map(|a| match a {
+                        Value::List(items) => items[i].clone(),
+                        a => a.clone(),
+                    })
+                    .collect();
+                self.call_builtin(name, args)
+            })
+            .collect::<Result<_, _>>()
+            .map(Value::List)
+    }
+
+    fn special_form(&self, name: &str, args: Vec<Expr>) -> Result<Value, SyaError> {
+        match (name, args.as_slice()) {
+            // An inner derivative is taken first, so `diff` can be nested.
+            ("diff", [Expr::Call(inner, args), Expr::Var(var)]) if inner == "diff" => {
+                let e = self.special_form(inner, args.clone())?.into_expr();
+                self.special_form(name, vec![e, Expr::Var(var.clone())])
+            }
+            ("diff", [e, Expr::Var(var)]) => Ok(Value::Expr(diff(
+                &self.substitute(e, var),
+                var,
+                self.angle,
+            )?)),
+            ("solve", [equation, Expr::Var(var), range @ ..]) => {
+                let f = match equation {
+                    Expr::Binary('=', lhs, rhs) => Expr::Binary('-', lhs.clone(), rhs.clone()),
+                    e => e.clone(),
+                };
+                self.find_root(name, &f, var, range)
+            }
+            ("root", [f, range @ ..]) => {
+                let var = self.free_var(name, f)?;
+                self.find_root(name, f, &var, range)
+            }
+            _ => Err(SyaError::InvalidArguments(name.to_string())),
+        }
+    }
+
+    /// Finds a root of `f` in `var` with Brent's method when `range` is an
+    /// interval, or Newton's method from a single starting point. Newton
+    /// starts at the current value of `var`, or 1, when there's no range.
+    fn find_root(
+        &self,
+        name: &str,
+        f: &Expr,
+        var: &str,
+        range: &[Expr],
+    ) -> Result<Value, SyaError> {
+        let f = self.substitute(f, var);
+
+        let root = match range {
+            [a, b] => brent(
+                |x| self.eval_at(&f, var, x),
+                self.eval_f64(a)?,
+                self.eval_f64(b)?,
+                &self.solver,
+            )?,
+            [x0] => self.newton(&f, var, self.eval_f64(x0)?)?,
+            [] => {
+                let x0 = match self.vars.get(var) {
+                    Some(Value::Number(n)) => n.as_f64(),
+                    _ => 1.0,
+                };
+                self.newton(&f, var, x0)?
+            }
+            _ => return Err(SyaError::InvalidArguments(name.to_string())),
+        };
+        Ok(Value::Number(Number::Float(root)))
+    }
+
+    fn newton(&self, f: &Expr, var: &str, x0: f64) -> Result<f64, SyaError> {
+        let at = |e: &Expr, x: f64| self.eval_at(e, var, x);
+        match diff(f, var, self.angle) {
+            Ok(df) => newton(|x| at(f, x), |x| at(&df, x), x0, &self.solver),
+            // Falls back to a central difference when there's no symbolic
+            // derivative.
+            Err(_) => {
+                let slope = |x: f64| {
+                    let h = 1e-6 * (1.0 + x.abs());
+                    Ok((at(f, x + h)? - at(f, x - h)?) / (2.0 * h))
+                };
+                newton(|x| at(f, x), slope, x0, &self.solver)
+            }
+        }
+    }
+
+    fn eval_at(&self, e: &Expr, var: &str, x: f64) -> Result<f64, SyaError> {
+        self.eval_f64(&e.replace(var, &Expr::Number(Number::Float(x))))
+    }
+
+    fn eval_f64(&self, e: &Expr) -> Result<f64, SyaError> {
+        match self.run(&e.to_rpn(), None)? {
+            Value::Number(n) => Ok(n.as_f64()),
+            Value::Expr(e) => Err(SyaError::UnknownVariable(e.vars().join(", "))),
+            v => Err(SyaError::ExpectedNumber(v.to_string())),
+        }
+    }
+
+    /// The variable `root` solves for: the only unbound variable in `f`, or
+    /// its only variable.
+    fn free_var(&self, name: &str, f: &Expr) -> Result<String, SyaError> {
+        let vars = f.vars();
+        let free: Vec<&String> = vars.iter().filter(|v| self.lookup(v).is_err()).collect();
+        match (free.as_slice(), vars.as_slice()) {
+            ([var], _) => Ok(var.to_string()),
+            ([], [var]) => Ok(var.to_string()),
+            _ => Err(SyaError::InvalidArguments(name.to_string())),
+        }
+    }
+
+    /// Replaces the variables in `expr` with their values, except for `keep`.
+    /// Constants are kept by name.
+    fn substitute(&self, expr: &Expr, keep: &str) -> Expr {
+        match expr {
+            Expr::Var(name) if name != keep && constants::get(name).is_none() => {
+                match self.lookup(name) {
+                    Ok(v) => v.into_expr(),
+                    Err(_) => expr.clone(),
+                }
+            }
+            Expr::Unary(s, e) => Expr::Unary(*s, Box::new(self.substitute(e, keep))),
+            Expr::Quantity(e, unit) => {
+                Expr::Quantity(Box::new(self.substitute(e, keep)), unit.clone())
+            }
+            Expr::Convert(e, unit) => {
+                Expr::Convert(Box::new(self.substitute(e, keep)), unit.clone())
+            }
+            Expr::Binary(s, a, b) => Expr::Binary(
+                *s,
+                Box::new(self.substitute(a, keep)),
+                Box::new(self.substitute(b, keep)),
+            ),
+            Expr::Call(name, args) => Expr::Call(
+                name.clone(),
+                args.iter().map(|a| self.substitute(a, keep)).collect(),
+            ),
+            _ => expr.clone(),
+        }
     }
 
     fn rpn(&mut self) -> Result<(), SyaError> {
         let mut holding_stack: Vec<&Token> = Vec::new();
+        // Argument count of the function each open parenthesis or bracket
+        // belongs to.
+        let mut calls: Vec<Option<usize>> = Vec::new();
+        let mut brackets: Vec<Bracket> = Vec::new();
+        let mut previous: Option<&Token> = None;
         for token in &self.input {
             match token {
-                Token::Number(_) => self.rpn_stack.push(token.clone()),
-                Token::OPEN | Token::UNARY(_) => holding_stack.push(token),
-                Token::CLOSE => {
-                    while let Some(&last) = holding_stack.last() {
-                        if last == &Token::OPEN {
-                            break;
+                // Units apply to the value right before them, which is
+                // already in the output.
+                Token::Number(_) | Token::Date(_) | Token::Ident(_) | Token::Unit(_) => {
+                    self.rpn_stack.push(token.clone())
+                }
+                // Converts everything before it, up to the open parenthesis.
+                Token::Convert(_) => {
+                    pop_until_open(&mut self.rpn_stack, &mut holding_stack);
+                    self.rpn_stack.push(token.clone());
+                }
+                // Equations like `solve(x ^ 2 = 2, x)` are only valid as
+                // function arguments, where `=` binds the loosest.
+                Token::ASSIGN if matches!(calls.last(), Some(Some(_))) => {
+                    pop_until_open(&mut self.rpn_stack, &mut holding_stack);
+                    holding_stack.push(token);
+                }
+                Token::ASSIGN | Token::Call(..) => {
+                    return Err(SyaError::InvalidToken(token.clone()))
+                }
+                Token::Function(_) | Token::UNARY(_) => holding_stack.push(token),
+                Token::OPEN => {
+                    let call = match previous {
+                        Some(Token::Function(_)) => Some(1),
+                        _ => None,
+                    };
+                    calls.push(call);
+                    holding_stack.push(token);
+                }
+                Token::LBRACKET => {
+                    // The indexed value is already in the output, as the
+                    // first argument.
+                    let (bracket, argc) = match previous {
+                        Some(
+                            Token::CLOSE
+                            | Token::RBRACKET
+                            | Token::Number(_)
+                            | Token::Date(_)
+                            | Token::Ident(_),
+                        ) => (Bracket::Index, 2),
+                        _ => (Bracket::List, 1),
+                    };
+                    brackets.push(bracket);
+                    calls.push(Some(argc));
+                    holding_stack.push(token);
+                }
+                Token::COLON => {
+                    if previous == Some(&Token::LBRACKET) {
+                        self.rpn_stack.push(Token::Number(Number::Integer(0)));
+                    }
+                    pop_until_open(&mut self.rpn_stack, &mut holding_stack);
+                    match (brackets.last_mut(), calls.last_mut()) {
+                        (Some(bracket @ Bracket::Index), Some(Some(argc))) if *argc == 2 => {
+                            *bracket = Bracket::Slice;
+                            *argc += 1;
                         }
-                        self.rpn_stack.push(last.clone());
-                        holding_stack.pop();
+                        _ => return Err(SyaError::InvalidToken(token.clone())),
+                    }
+                }
+                Token::RBRACKET => {
+                    pop_until_open(&mut self.rpn_stack, &mut holding_stack);
+                    match holding_stack.pop() {
+                        Some(Token::LBRACKET) => {}
+                        Some(_) => return Err(SyaError::ExpectedChar(')')),
+                        None => return Err(SyaError::ExpectedChar('[')),
+                    };
+                    let argc = match (calls.pop(), previous) {
+                        (_, Some(Token::LBRACKET)) => 0,
+                        // An open slice, like `v[1:]`, runs to the end.
+                        (Some(Some(argc)), Some(Token::COLON)) => argc - 1,
+                        (Some(Some(argc)), _) => argc,
+                        _ => 0,
+                    };
+                    let name = match brackets.pop() {
+                        Some(Bracket::Index) => "index",
+                        Some(Bracket::Slice) => "slice",
+                        _ => "list",
+                    };
+                    self.rpn_stack.push(Token::Call(name.to_string(), argc));
+                }
+                Token::COMMA => {
+                    pop_until_open(&mut self.rpn_stack, &mut holding_stack);
+                    match calls.last_mut() {
+                        Some(Some(argc)) => *argc += 1,
+                        _ => return Err(SyaError::InvalidToken(token.clone())),
                     }
+                }
+                Token::CLOSE => {
+                    pop_until_open(&mut self.rpn_stack, &mut holding_stack);
                     match holding_stack.last() {
+                        Some(Token::LBRACKET) => return Err(SyaError::ExpectedChar(']')),
                         Some(_) => holding_stack.pop(),
                         None => return Err(SyaError::ExpectedChar('(')),
                     };
+                    if let Some(Some(argc)) = calls.pop() {
+                        let argc = match previous {
+                            Some(Token::OPEN) => 0,
+                            _ => argc,
+                        };
+                        if let Some(Token::Function(name)) = holding_stack.pop() {
+                            self.rpn_stack.push(Token::Call(name.clone(), argc));
+                        }
+                    }
                 }
                 Token::Operator(o) => {
                     while let Some(&last) = holding_stack.last() {
@@ -107,10 +710,25 @@ impl Sya {
                     holding_stack.push(token);
                 }
             }
+            if let Some(trace) = self.trace.as_mut() {
+                trace.rpn.push(RpnStep {
+                    token: token.clone(),
+                    output: self.rpn_stack.clone(),
+                    holding: holding_stack.iter().map(|&t| t.clone()).collect(),
+                });
+            }
+            previous = Some(token);
         }
 
         while let Some(o) = holding_stack.pop() {
             self.rpn_stack.push(o.clone());
+            if let Some(trace) = self.trace.as_mut() {
+                trace.rpn.push(RpnStep {
+                    token: o.clone(),
+                    output: self.rpn_stack.clone(),
+                    holding: holding_stack.iter().map(|&t| t.clone()).collect(),
+                });
+            }
         }
         Ok(())
     }
@@ -118,14 +736,130 @@ impl Sya {
     pub fn rpn_formatted(&self) -> String {
         self.rpn_stack
             .iter()
-            .map(|token| match token {
-                Token::Number(n) => n.to_string(),
-                Token::Operator(o) => o.sign.to_string(),
-                Token::UNARY(s) => format!("u{}", s),
-                Token::OPEN => "(".to_string(),
-                Token::CLOSE => ")".to_string(),
-            })
+            .map(|token| token.to_string())
             .collect::<Vec<_>>()
             .join(" ")
     }
 }
+
+fn pop_until_open(rpn_stack: &mut Vec<Token>, holding_stack: &mut Vec<&Token>) {
+    while let Some(&last) = holding_stack.last() {
+        if matches!(last, Token::OPEN | Token::LBRACKET) {
+            break;
+        }
+        rpn_stack.push(last.clone());
+        holding_stack.pop();
+    }
+}
+
+/// Splits `input` into statements on every `;` before a `#` comment, which
+/// stays in the last statement.
+pub fn split_statements(input: &str) -> Vec<&str> {
+    let code = input.find('#').unwrap_or(input.len());
+    let mut sources: Vec<&str> = input[..code].split(';').collect();
+    if let Some(last) = sources.last_mut() {
+        let start = code - last.len();
+        *last = &input[start..];
+    }
+    sources
+}
+
+/// Functions that receive their arguments unevaluated.
+fn is_special(name: &str) -> bool {
+    matches!(name, "diff" | "solve" | "root")
+}
+
+/// Finds where the `argc` operands consumed by the token at `end` start.
+fn operand_start(rpn: &[Token], end: usize, argc: usize) -> usize {
+    let mut needed = argc;
+    let mut start = end;
+    while needed > 0 && start > 0 {
+        start -= 1;
+        needed = needed - 1
+            + match &rpn[start] {
+                Token::UNARY(_) | Token::Unit(_) | Token::Convert(_) => 1,
+                Token::Operator(_) | Token::ASSIGN => 2,
+                Token::Call(_, n) => *n,
+                _ => 0,
+            };
+    }
+    start
+}
+
+/// Maps the start of the arguments of every special form to the position of
+/// its call, keeping the outermost one when several start at the same token.
+fn quoted_ranges(rpn: &[Token]) -> HashMap<usize, usize> {
+    let mut quoted = HashMap::new();
+    for (i, token) in rpn.iter().enumerate() {
+        if let Token::Call(name, argc) = token {
+            if is_special(name) {
+                let start = operand_start(rpn, i, *argc);
+                let end = quoted.entry(start).or_insert(i);
+                *end = (*end).max(i);
+            }
+        }
+    }
+    quoted
+}
+
+/// What a pair of brackets builds.
+enum Bracket {
+    List,
+    Index,
+    Slice,
+}
+
+/// What a statement assigns to.
+enum Target {
+    Var(String),
+    Function(String, Vec<String>),
+}
+
+/// Splits `name = ...` and `name(params) = ...` statements into their target
+/// and the expression.
+fn split_assignment(tokens: Vec<Token>) -> (Option<Target>, Vec<Token>) {
+    match tokens.as_slice() {
+        [Token::Ident(name), Token::ASSIGN, rest @ ..] => {
+            (Some(Target::Var(name.clone())), rest.to_vec())
+        }
+        [Token::Function(name), Token::OPEN, rest @ ..] => {
+            let close = match rest.iter().position(|t| *t == Token::CLOSE) {
+                Some(close) if rest.get(close + 1) == Some(&Token::ASSIGN) => close,
+                _ => return (None, tokens),
+            };
+            let mut params = Vec::new();
+            for (i, token) in rest[..close].iter().enumerate() {
+                match (i % 2, token) {
+                    (0, Token::Ident(param)) => params.push(param.clone()),
+                    (1, Token::COMMA) => {}
+                    _ => return (None, tokens),
+                }
+            }
+            if close % 2 == 0 && close > 0 {
+                return (None, tokens);
+            }
+            let target = Target::Function(name.clone(), params);
+            (Some(target), rest[close + 2..].to_vec())
+        }
+        _ => (None, tokens),
+    }
+}
+
+/// The elements of the lists in `values`, nested ones included, in order.
+fn flatten(values: Vec<Value>) -> Vec<Value> {
+    values
+        .into_iter()
+        .flat_map(|v| match v {
+            Value::List(items) => flatten(items),
+            v => vec![v],
+        })
+        .collect()
+}
+
+fn in_statement(many: bool, index: usize, e: SyaError) -> SyaError {
+    if many {
+        SyaError::Statement(index + 1, Box::new(e))
+    } else {
+        e
+    }
+}
@@ -1,7 +1,11 @@
 use crate::{
+    cli::{parse_args, Command, Options, Source},
+    editor::{read_key, Editor, Key},
     errors::SyaError,
     number::Number,
+    simplify::SimplifyOptions,
     tokenizer::{Operator, Precedence, Token, Tokenizer},
+    value::Value,
 };
 
 use super::*;
@@ -51,24 +55,24 @@ fn test_unary_parse() {
 fn test_unary_results() {
     let mut sya = Sya::new("--5").expect("Should Construct");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(5))), sya.out);
 
     sya.new_input("-(-5)").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(5))), sya.out);
 
     sya.new_input("--+++--+-+9").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(-9)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(-9))), sya.out);
 
     sya.new_input("-(-(5+(8-3))*(+4^2))").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(160)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(160))), sya.out);
 
     sya.new_input("-(10*(2+-3))/-(4-(2*+3))")
         .expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(5))), sya.out);
 }
 
 #[test]
@@ -91,21 +95,21 @@ fn test_calculate() {
     let mut sya = Sya::new("1 + 2 * 4 - 3").expect("Should Construct");
 
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(6)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(6))), sya.out);
 
     sya.new_input("1 - 2").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(-1)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(-1))), sya.out);
 
     sya.new_input("-5 * 39 - (10 + 1) / 2")
         .expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Float(-200.5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Float(-200.5))), sya.out);
 
     sya.new_input("(1 * 100) - - (3 ^ (4 / 2 + 1) + (2)) * + 30 ^ 1")
         .expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(970)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(970))), sya.out);
 
     sya.new_input("").expect("Should Parse");
     assert_eq!(Err(SyaError::InvalidInput), sya.calculate());
@@ -117,15 +121,15 @@ fn test_paren() {
     let mut sya = Sya::new("5 * (1 + 2)").expect("Should Construct");
 
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(15)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(15))), sya.out);
 
     sya.new_input("(1 + (1 + 2))").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(4)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(4))), sya.out);
 
     sya.new_input("(((((1)))))").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(1)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(1))), sya.out);
 
     sya.new_input("5 ) + 1").expect("Should Parse");
     assert_eq!(Err(SyaError::ExpectedChar('(')), sya.calculate());
@@ -136,21 +140,1525 @@ fn test_float() {
     let mut sya = Sya::new("2.5 + 1").expect("Should Construct");
 
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Float(3.5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Float(3.5))), sya.out);
 
     sya.new_input("10 / 2").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Integer(5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Integer(5))), sya.out);
 
     sya.new_input("3.5 * 2").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Float(7.0)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Float(7.0))), sya.out);
 
     sya.new_input("7 - 2.5").expect("Should Parse");
     assert_eq!(Ok(()), sya.calculate());
-    assert_eq!(Some(Number::Float(4.5)), sya.out);
+    assert_eq!(Some(Value::Number(Number::Float(4.5))), sya.out);
 
     sya.new_input("5 / 0").expect("Should Parse");
     assert_eq!(Err(SyaError::DivisionByZero), sya.calculate());
     assert_eq!(None, sya.out);
 }
+
+#[test]
+fn test_trace() {
+    let mut sya = Sya::new("2 * (1 + 3)").expect("Should Construct");
+    sya.set_trace(true);
+    sya.new_input("2 * (1 + 3)").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+
+    let trace = sya.trace.as_ref().expect("Should Trace");
+    assert_eq!(trace.rpn.len(), 8);
+    let plus = &trace.rpn[4];
+    assert_eq!(Token::Operator(op('+', Precedence::SUM)), plus.token);
+    assert_eq!(
+        vec![
+            Token::Number(Number::Integer(2)),
+            Token::Number(Number::Integer(1))
+        ],
+        plus.output
+    );
+    assert_eq!(3, plus.holding.len());
+    let drained = trace.rpn.last().expect("Should Have Steps");
+    assert_eq!(Token::Operator(op('*', Precedence::MUL)), drained.token);
+    assert_eq!(5, drained.output.len());
+    assert!(drained.holding.is_empty());
+
+    let last = trace.eval.last().expect("Should Have Steps");
+    assert_eq!(Token::Operator(op('*', Precedence::MUL)), last.token);
+    assert_eq!(
+        vec![
+            Value::Number(Number::Integer(2)),
+            Value::Number(Number::Integer(4))
+        ],
+        last.before
+    );
+    assert_eq!(vec![Value::Number(Number::Integer(8))], last.after);
+    assert!(!trace.tables().contains("Statement"));
+
+    // Each statement gets its own tables
+    sya.new_input("a = 2; a * 3").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    let tables = sya.trace.as_ref().expect("Should Trace").tables();
+    let first = tables.find("Statement 1").expect("Should Have Header");
+    let second = tables.find("Statement 2").expect("Should Have Header");
+    assert!(first < second && tables[second..].contains("a 3 *"));
+    assert!(!tables[..second].contains("a 3 *"));
+
+    sya.set_trace(false);
+    sya.new_input("1 + 1").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(None, sya.trace);
+}
+
+#[test]
+fn test_statements() {
+    let mut sya = Sya::new("a = 3; b = a * 2; a + b").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(9))), sya.out);
+    assert_eq!(Some(&Value::Number(Number::Integer(6))), sya.vars.get("b"));
+
+    sya.new_input("a - 1;").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(2))), sya.out);
+
+    sya.new_input("c = 1; 2 / 0").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::Statement(2, Box::new(SyaError::DivisionByZero))),
+        sya.calculate()
+    );
+
+    sya.new_input("1 + d").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::UnknownVariable("d".to_string())),
+        sya.calculate()
+    );
+
+    assert_eq!(
+        Err(SyaError::Statement(3, Box::new(SyaError::InvalidChar('$')))),
+        sya.new_input("1; 2; $")
+    );
+}
+
+#[test]
+fn test_simplify() {
+    use crate::number::Mode;
+    let mut sya = Sya::new("(x * 1 + 0) ^ 1 - (2 * 3 + y / 1)").expect("Should Construct");
+    let safe = SimplifyOptions::default();
+    assert_eq!(Ok("x - (6 + y)".to_string()), sya.simplified(&safe));
+
+    sya.new_input("a = 0 - -(x * (4 - 2 ^ 2)); 1 / 0 + x")
+        .expect("Should Parse");
+    assert_eq!(
+        Ok("a = x * 0; 1 / 0 + x".to_string()),
+        sya.simplified(&safe)
+    );
+
+    let aggressive = SimplifyOptions {
+        aggressive: true,
+        ..Default::default()
+    };
+    sya.new_input("x * 0 + (y - y) + z ^ 0")
+        .expect("Should Parse");
+    assert_eq!(Ok("1".to_string()), sya.simplified(&aggressive));
+
+    sya.simplify = Some(safe);
+    sya.new_input("x = 2.5; (x + 0) * 1 * 2")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Float(5.0))), sya.out);
+    assert_eq!(sya.rpn_formatted(), "x 2 *");
+
+    for mode in [Mode::Int, Mode::Exact] {
+        let input = "x = 3; 7 / 2 * x + 1 / 4";
+        let mut plain = Sya::new(input).expect("Should Construct");
+        plain.mode = mode;
+        let mut folded = Sya::new(input).expect("Should Construct");
+        folded.mode = mode;
+        folded.simplify = Some(SimplifyOptions::default());
+        assert_eq!(Ok(()), plain.calculate());
+        assert_eq!(Ok(()), folded.calculate());
+        assert_eq!(plain.out, folded.out);
+    }
+}
+
+#[test]
+fn test_functions_parse() {
+    let mut sya = Sya::new("f(1, -2 * 3, g()) + h((4), 5)").expect("Should Construct");
+    assert_eq!(
+        Err(SyaError::UnknownFunction("g".to_string())),
+        sya.calculate()
+    );
+    assert_eq!(sya.rpn_formatted(), "1 2 u- 3 * g f 4 5 h +");
+
+    sya.new_input("1, 2").expect("Should Parse");
+    assert_eq!(Err(SyaError::InvalidToken(Token::COMMA)), sya.calculate());
+}
+
+#[test]
+fn test_diff() {
+    let mut sya = Sya::new("diff(x ^ 3 + 2 * x - 7, x)").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("3 * x ^ 2 + 2", sya.out.as_ref().unwrap().to_string());
+
+    sya.new_input("diff((x + 1) / x, x)").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(
+        "(x - (x + 1)) / x ^ 2",
+        sya.out.as_ref().unwrap().to_string()
+    );
+
+    sya.new_input("a = 3; d = diff(a * (2 * x + 1) ^ 2, x); diff(d, x) / 4")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(6))), sya.out);
+    assert_eq!("12 * (2 * x + 1)", sya.vars.get("d").unwrap().to_string());
+
+    sya.new_input("x = 5; diff(x * y, x)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("y", sya.out.as_ref().unwrap().to_string());
+
+    sya.new_input("diff(e ^ x, x)").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("e ^ x", sya.out.as_ref().unwrap().to_string());
+
+    sya.new_input("diff(2 ^ (3 * x), x)").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(
+        "2 ^ (3 * x) * ln(2) * 3",
+        sya.out.as_ref().unwrap().to_string()
+    );
+
+    sya.new_input("diff(x ^ x, x)").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("x ^ x * (ln(x) + 1)", sya.out.as_ref().unwrap().to_string());
+
+    sya.new_input("diff(diff(x ^ 3, x), x)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("6 * x", sya.out.as_ref().unwrap().to_string());
+
+    sya.new_input("diff(diff(floor(x), x), x)")
+        .expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::NotDifferentiable("floor(x)".to_string())),
+        sya.calculate()
+    );
+
+    sya.new_input("diff(x, 2)").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::InvalidArguments("diff".to_string())),
+        sya.calculate()
+    );
+}
+
+#[test]
+fn test_solve() {
+    fn close(sya: &Sya, expected: f64) -> bool {
+        match sya.out {
+            Some(Value::Number(Number::Float(f))) => (f - expected).abs() < 1e-9,
+            _ => false,
+        }
+    }
+
+    let mut sya = Sya::new("solve(x ^ 2 = 2, x)").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(close(&sya, 2f64.sqrt()));
+
+    sya.new_input("solve(x ^ 2 = 2, x, -3, 0)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(close(&sya, -(2f64.sqrt())));
+
+    sya.new_input("fixed = 1000; solve(12 * q = 4 * q + fixed, q)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(close(&sya, 125.0));
+
+    sya.new_input("root(x ^ 3 - x - 1, 1)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(close(&sya, 1.324717957244746));
+
+    // The unknown can be in an exponent, which is then a float
+    sya.new_input("solve(1.05 ^ n = 2, n)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(close(&sya, 2f64.ln() / 1.05f64.ln()));
+
+    sya.new_input("solve(e ^ x = 2, x, 0, 1)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(close(&sya, 2f64.ln()));
+
+    sya.new_input("root(x ^ 2 + 1, 0)").expect("Should Parse");
+    assert_eq!(Err(SyaError::NoConvergence(100)), sya.calculate());
+
+    sya.new_input("root(x ^ 2 - 4, 3, 5)")
+        .expect("Should Parse");
+    assert_eq!(Err(SyaError::NotBracketed(3.0, 5.0)), sya.calculate());
+
+    sya.new_input("x = 2 = 3").expect("Should Parse");
+    assert_eq!(Err(SyaError::InvalidToken(Token::ASSIGN)), sya.calculate());
+
+    // Large roots are only known to their float spacing, so the tolerance is
+    // relative
+    sya.solver.tolerance = 1e-10;
+    sya.solver.max_iterations = 200;
+    sya.new_input("solve(x ^ 2 = 2 * 10 ^ 16, x, 0, 200000000)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    match sya.out {
+        Some(Value::Number(Number::Float(f))) => {
+            assert!((f / 1e8 / 2f64.sqrt() - 1.0).abs() < 1e-9)
+        }
+        ref out => panic!("Expected a float, got {:?}", out),
+    }
+
+    sya.solver.max_iterations = 2;
+    sya.new_input("root(x ^ 3 - x - 1, 1, 2)")
+        .expect("Should Parse");
+    assert_eq!(Err(SyaError::NoConvergence(2)), sya.calculate());
+}
+
+#[test]
+fn test_cli_args() {
+    let args = |a: &[&str]| parse_args(a.iter().map(|s| s.to_string()));
+    let run = |source| Ok(Command::Run(source, Options::default()));
+
+    assert_eq!(run(Source::Stdin), args(&[]));
+    assert_eq!(
+        run(Source::Args(vec!["1+2".to_string(), "-3".to_string()])),
+        args(&["1+2", "-3"])
+    );
+    assert_eq!(
+        run(Source::File("exprs.txt".to_string())),
+        args(&["-f", "exprs.txt"])
+    );
+    assert_eq!(
+        run(Source::Args(vec!["-f".to_string()])),
+        args(&["--", "-f"])
+    );
+    assert_eq!(
+        Ok(Command::Run(
+            Source::Stdin,
+            Options {
+                json: true,
+                ..Options::default()
+            }
+        )),
+        args(&["--json"])
+    );
+    assert_eq!(Ok(Command::Help), args(&["1", "--help"]));
+
+    // Flags go before or after `run`
+    let script = |options| {
+        Ok(Command::Run(
+            Source::Script("calc.sya".to_string()),
+            options,
+        ))
+    };
+    assert_eq!(
+        script(Options {
+            no_config: true,
+            ..Options::default()
+        }),
+        args(&["--no-config", "run", "calc.sya"])
+    );
+    assert_eq!(
+        script(Options {
+            json: true,
+            verbose: true,
+            ..Options::default()
+        }),
+        args(&["--json", "run", "-v", "calc.sya"])
+    );
+    assert_eq!(
+        script(Options {
+            verbose: true,
+            ..Options::default()
+        }),
+        args(&["run", "calc.sya", "-v"])
+    );
+    assert_eq!(
+        Err("Missing file after 'run'".to_string()),
+        args(&["run", "-v"])
+    );
+    assert!(args(&["run", "calc.sya", "1"]).is_err());
+    assert!(args(&["-f"]).is_err());
+    assert!(args(&["-f", "exprs.txt", "1"]).is_err());
+
+    assert!(SyaError::Statement(2, Box::new(SyaError::ExpectedChar('('))).is_syntax());
+    assert!(!SyaError::DivisionByZero.is_syntax());
+}
+
+#[test]
+fn test_read_key() {
+    let mut input: &[u8] = b"a\x1b[D\x1b[3~\x01\x7f\xc3\xa9\x1bOH\r";
+    let mut keys = Vec::new();
+    while let Some(key) = read_key(&mut input).expect("Should Read") {
+        keys.push(key);
+    }
+    assert_eq!(
+        vec![
+            Key::Char('a'),
+            Key::Left,
+            Key::Delete,
+            Key::Ctrl('a'),
+            Key::Backspace,
+            Key::Char('\u{e9}'),
+            Key::Home,
+            Key::Enter
+        ],
+        keys
+    );
+}
+
+#[test]
+fn test_editor() {
+    let mut editor = Editor::default();
+    editor.history = vec![
+        "1 + 2".to_string(),
+        "a = 4".to_string(),
+        "a * 3".to_string(),
+    ];
+    let edit = |keys: &[u8]| {
+        let mut input = keys;
+        editor
+            .edit("> ", &mut input, &mut Vec::new())
+            .expect("Should Edit")
+    };
+
+    assert_eq!(Some("132".to_string()), edit(b"12\x1b[D3\r"));
+    assert_eq!(Some("x + 1".to_string()), edit(b"1\x01x + \r"));
+    assert_eq!(Some("a = 4".to_string()), edit(b"\x1b[A\x1b[A\r"));
+    assert_eq!(Some("new".to_string()), edit(b"new\x1b[A\x1b[B\r"));
+    assert_eq!(Some("1 * 2".to_string()), edit(b"1 + 2\x7f\x7f\x7f* 2\r"));
+    assert_eq!(Some("a = 4".to_string()), edit(b"\x12a\x12\r"));
+    assert_eq!(Some("1 + 2".to_string()), edit(b"\x12+\x1b[C\r"));
+    assert_eq!(Some("1 ".to_string()), edit(b"1 + 2 \x17\x17\r"));
+    assert_eq!(None, edit(b"\x04"));
+    assert_eq!(None, edit(b""));
+}
+
+#[test]
+fn test_results() {
+    let mut sya = Sya::new("2 * 3").expect("Should Construct");
+    assert_eq!(
+        Err(SyaError::UnknownResult("ans".to_string())),
+        sya.lookup("ans")
+    );
+    assert_eq!(Ok(()), sya.calculate());
+
+    sya.new_input("ans + 1").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(7))), sya.out);
+
+    sya.new_input("$1 * $2 - ans").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(35))), sya.out);
+    assert_eq!(3, sya.results.len());
+
+    sya.new_input("$4").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::UnknownResult("$4".to_string())),
+        sya.calculate()
+    );
+
+    sya.new_input("ans = 1").expect("Should Parse");
+    assert_eq!(Err(SyaError::ReadOnly("ans".to_string())), sya.calculate());
+    assert_eq!(3, sya.results.len());
+}
+
+#[test]
+fn test_commands() {
+    use crate::commands::{parse_command, Command};
+    use crate::number::Mode;
+
+    assert_eq!(Ok(None), parse_command("1 + 2"));
+    assert_eq!(Ok(Some(Command::Quit)), parse_command("q!"));
+    assert_eq!(Ok(Some(Command::Rpn(false))), parse_command(":rpn off"));
+    assert_eq!(
+        Ok(Some(Command::Mode(Mode::Exact))),
+        parse_command(":mode exact")
+    );
+    assert_eq!(
+        Ok(Some(Command::Precision(Some(3)))),
+        parse_command(":precision 3")
+    );
+    assert_eq!(
+        Ok(Some(Command::Precision(None))),
+        parse_command(":precision off")
+    );
+    assert_eq!(
+        Ok(Some(Command::Tolerance(1e-6))),
+        parse_command(":tolerance 1e-6")
+    );
+    assert_eq!(
+        Ok(Some(Command::Iterations(500))),
+        parse_command(":iterations 500")
+    );
+    assert_eq!(
+        Ok(Some(Command::Show(":tolerance"))),
+        parse_command(":tolerance")
+    );
+    assert_eq!(
+        Err("Usage: :tolerance X".to_string()),
+        parse_command(":tolerance -1")
+    );
+    assert_eq!(
+        Err("Usage: :iterations N".to_string()),
+        parse_command(":iterations 0")
+    );
+    assert_eq!(
+        Ok(Some(Command::Simplify("x * 1", true))),
+        parse_command(":simplify! x * 1")
+    );
+    assert_eq!(
+        Err("Usage: :mode auto|int|float|exact".to_string()),
+        parse_command(":mode fast")
+    );
+    assert_eq!(
+        Err("Unknown command ':vasr', did you mean :vars?".to_string()),
+        parse_command(":vasr")
+    );
+    assert_eq!(
+        Err("Unknown command ':c', did you mean :clear?".to_string()),
+        parse_command(":c")
+    );
+    assert_eq!(
+        Err("Unknown command ':foo', see :help".to_string()),
+        parse_command(":foo")
+    );
+}
+
+#[test]
+fn test_modes() {
+    use crate::number::Mode;
+
+    let calculate = |mode: Mode, input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.mode = mode;
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    assert_eq!(
+        Ok(Value::Number(Number::Rational(1, 2))),
+        calculate(Mode::Exact, "1/3 + 1/6")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Rational(3, 4))),
+        calculate(Mode::Exact, "0.25 * 3")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Integer(3))),
+        calculate(Mode::Int, "7 / 2")
+    );
+    assert_eq!(
+        Err(SyaError::NotInteger("2.5".to_string())),
+        calculate(Mode::Int, "2.5 + 1")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Float(4.0))),
+        calculate(Mode::Float, "2 * 2")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Float(3.5))),
+        calculate(Mode::Auto, "7 / 2")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Float(0.125))),
+        calculate(Mode::Auto, "2 ^ -3")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Rational(9, 4))),
+        calculate(Mode::Exact, "(2 / 3) ^ -2")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Float(8.0))),
+        calculate(Mode::Float, "2 ^ 3")
+    );
+    assert_eq!(
+        Ok(Value::Number(Number::Float(2f64.sqrt()))),
+        calculate(Mode::Auto, "2 ^ 0.5")
+    );
+    assert_eq!(
+        Err(SyaError::OutOfDomain(
+            "^".to_string(),
+            "-8, 0.5".to_string()
+        )),
+        calculate(Mode::Auto, "(-8) ^ 0.5")
+    );
+    assert_eq!(
+        Number::Float(0.333),
+        Number::Float(1.0 / 3.0).round_digits(3)
+    );
+}
+
+#[test]
+fn test_json() {
+    use crate::{format::Format, json};
+
+    assert_eq!("\"a\\\"b\\n\"", json::string("a\"b\n"));
+    assert_eq!(
+        r#"{"type":"integer","value":60}"#,
+        json::value(&Value::Number(Number::Integer(60)))
+    );
+    assert_eq!(
+        r#"{"type":"float","value":"NaN"}"#,
+        json::value(&Value::Number(Number::Float(f64::NAN)))
+    );
+
+    let mut sya = Sya::new("1 + 2; 4 / 0").expect("Should Construct");
+    assert_eq!(
+        SyaError::DivisionByZero.kind(),
+        sya.calculate().unwrap_err().kind()
+    );
+    assert_eq!(Some((7, 12)), sya.error_span);
+    assert_eq!(
+        Some(SyaError::InvalidChar('@')),
+        sya.new_input("12 + @ 3").err()
+    );
+    assert_eq!(Some((5, 6)), sya.error_span);
+
+    let outcome = run(
+        &mut sya,
+        &mut Settings {
+            json: true,
+            ..Settings::default()
+        },
+        "2 * 30",
+    );
+    assert_eq!(
+        r#"{"input":"2 * 30","tokens":["2","*","30"],"rpn":"2 30 *","result":{"type":"integer","value":60}}"#,
+        json_outcome(&sya, &Format::default(), "2 * 30", &outcome)
+    );
+    // Tokenizer errors keep the tokens read before the error
+    let outcome = run(&mut sya, &mut Settings::default(), "1; 12 + @ 3");
+    assert!(
+        json_outcome(&sya, &Format::default(), "1; 12 + @ 3", &outcome)
+            .contains(r#""tokens":["1",";","12","+"]"#)
+    );
+}
+
+#[test]
+fn test_highlight() {
+    use crate::color::{highlight, mark};
+    use crate::units::Units;
+
+    let units = Units::default();
+    let mut tokenizer = Tokenizer::new("sin(x) + $1");
+    tokenizer.parse().expect("Should Parse");
+    let spans: Vec<(usize, usize)> = tokenizer.spanned().map(|(_, span)| span).collect();
+    assert_eq!(vec![(0, 3), (3, 4), (4, 5), (5, 6), (7, 8), (9, 11)], spans);
+
+    assert_eq!(
+        "\x1b[36m1\x1b[0m \x1b[33m+\x1b[0m x",
+        highlight("1 + x", None, &units)
+    );
+    assert_eq!(
+        "\x1b[1;7m(\x1b[0m\x1b[36m2\x1b[0m\x1b[1;7m)\x1b[0m\x1b[1;31m)\x1b[0m",
+        highlight("(2))", Some(3), &units)
+    );
+    assert_eq!(
+        "\x1b[32m:rpn\x1b[0m off \x1b[1;31m@\x1b[0m",
+        highlight(":rpn off @", None, &units)
+    );
+    assert_eq!(
+        "\x1b[36m1\x1b[0m \x1b[2m# (\x1b[0m",
+        highlight("1 # (", None, &units)
+    );
+    assert_eq!("1 + \x1b[1;4;31m#\x1b[0m", mark("1 + #", (4, 5)));
+}
+
+#[test]
+fn test_completion() {
+    let mut sya = Sya::new("alpha = 1; alps = 2").expect("Should Construct");
+    sya.calculate().expect("Should Calculate");
+    let mut editor = Editor::default();
+    editor.completions = completions(&sya);
+    assert!(editor.completions.contains(&(
+        "solve(".to_string(),
+        "solve(equation, var[, x0 | a, b])".to_string()
+    )));
+
+    let edit = |keys: &[u8]| {
+        let mut input = keys;
+        let mut out = Vec::new();
+        let line = editor
+            .edit("> ", &mut input, &mut out)
+            .expect("Should Edit");
+        (line.unwrap_or_default(), String::from_utf8(out).unwrap())
+    };
+    assert_eq!("alp", edit(b"al\t\r").0);
+    assert_eq!("a", edit(b"a\t\r").0);
+    assert_eq!("alpha + 1", edit(b"alph\t + 1\r").0);
+    assert_eq!("diff(x, x)", edit(b"di\tx, x)\r").0);
+    assert_eq!(":history", edit(b":hi\t\r").0);
+    assert_eq!("zz", edit(b"zz\t\r").0);
+
+    let (line, out) = edit(b"alp\t\r");
+    assert_eq!("alp", line);
+    assert!(out.contains("\r\nalpha  alps\r\n"));
+    let (_, out) = edit(b":s\t\t\r");
+    assert!(out.contains(":simplify <expression>  :simplify! <expression>"));
+}
+
+#[test]
+fn test_comments() {
+    use crate::sya::split_statements;
+
+    let mut tokenizer = Tokenizer::new("1 + 2 # three; (");
+    assert_eq!(3, tokenizer.parse().expect("Should Parse").len());
+    assert_eq!(
+        vec!["a = 1", " b = 2 # c; d"],
+        split_statements("a = 1; b = 2 # c; d")
+    );
+
+    let mut sya = Sya::new("a = 2; a * 3 # ; a").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(6))), sya.out);
+
+    let args = |a: &[&str]| parse_args(a.iter().map(|s| s.to_string()));
+    assert_eq!(
+        Ok(Command::Run(
+            Source::Script("calc.sya".to_string()),
+            Options {
+                verbose: true,
+                ..Options::default()
+            }
+        )),
+        args(&["run", "calc.sya", "-v"])
+    );
+    assert!(args(&["run"]).is_err());
+    assert!(args(&["run", "calc.sya", "1 + 2"]).is_err());
+}
+
+#[test]
+fn test_user_functions() {
+    let mut sya = Sya::new("sq(x) = x * x; area(w, h) = w * h").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("area(w, h) = w * h", sya.functions["area"].to_string());
+
+    sya.new_input("x = 10; sq(3) + area(2, x)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Integer(29))), sya.out);
+
+    // Arguments are bound at once, `y` isn't replaced again.
+    sya.new_input("area(y, 2)").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::UnknownVariable("y".to_string())),
+        sya.calculate()
+    );
+
+    sya.new_input("sq(1, 2)").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::InvalidArguments("sq".to_string())),
+        sya.calculate()
+    );
+    sya.new_input("f(x) = f(x) + 1; f(1)")
+        .expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::Statement(
+            2,
+            Box::new(SyaError::RecursionLimit("f".to_string()))
+        )),
+        sya.calculate()
+    );
+    sya.new_input("diff(x) = x").expect("Should Parse");
+    assert_eq!(Err(SyaError::ReadOnly("diff".to_string())), sya.calculate());
+
+    let results = sya.results.len();
+    assert_eq!(Ok(()), sya.define("k = 2"));
+    assert_eq!(results, sya.results.len());
+}
+
+#[test]
+fn test_config() {
+    use crate::config::{parse, Config};
+    use crate::format::Format;
+    use crate::number::{Angle, Mode, Rounding};
+    use crate::solve::SolverOptions;
+
+    let config = parse(
+        "# defaults\nmode = exact\nprecision = 4\nangle = deg\nrounding = half-even\ntolerance = 1e-9\niterations = 50\nrpn = off\nprompt = \"sya> \"\ncolor = on\n\n[definitions]\ng = 9.81\nkmh(ms) = ms * 3.6\n",
+    )
+    .expect("Should Parse");
+    assert_eq!(
+        Config {
+            mode: Mode::Exact,
+            format: Format {
+                precision: Some(4),
+                ..Format::default()
+            },
+            angle: Angle::Deg,
+            rounding: Rounding::HalfEven,
+            solver: SolverOptions {
+                tolerance: 1e-9,
+                max_iterations: 50,
+            },
+            echo_rpn: false,
+            prompt: "sya> ".to_string(),
+            color: Some(true),
+            definitions: vec!["g = 9.81".to_string(), "kmh(ms) = ms * 3.6".to_string()],
+        },
+        config
+    );
+    assert_eq!(Ok(Config::default()), parse(""));
+    assert_eq!(
+        Err("2: Invalid value 'fast' for 'mode'".to_string()),
+        parse("\nmode = fast")
+    );
+    assert_eq!(
+        Err("1: Invalid value '0' for 'iterations'".to_string()),
+        parse("iterations = 0")
+    );
+    assert_eq!(
+        Err("1: Unknown setting 'colour'".to_string()),
+        parse("colour = on")
+    );
+    assert_eq!(
+        Err("1: Unknown section '[vars]'".to_string()),
+        parse("[vars]")
+    );
+}
+
+#[test]
+fn test_format() {
+    use crate::format::{Format, Radix};
+
+    let number = |n: Number| Value::Number(n);
+    let mut format = Format::default();
+    assert_eq!("1234567", format.value(&number(Number::Integer(1234567))));
+    assert_eq!("15.5", format.value(&number(Number::Float(15.5))));
+    assert_eq!("1/3", format.value(&number(Number::Rational(1, 3))));
+
+    format.group = true;
+    format.types = true;
+    assert_eq!(
+        "-1,234,567 (integer)",
+        format.value(&number(Number::Integer(-1234567)))
+    );
+    assert_eq!(
+        "1,000.25 (float)",
+        format.value(&number(Number::Float(1000.25)))
+    );
+    assert_eq!("123 (integer)", format.value(&number(Number::Integer(123))));
+
+    format.types = false;
+    format.fractions = false;
+    format.precision = Some(3);
+    assert_eq!("0.333", format.value(&number(Number::Rational(1, 3))));
+    assert_eq!("inf", format.value(&number(Number::Float(f64::INFINITY))));
+
+    format.radix = Radix::Hex;
+    assert_eq!("-0x1_0000", format.value(&number(Number::Integer(-65536))));
+    format.radix = Radix::Bin;
+    format.group = false;
+    assert_eq!("0b1010", format.value(&number(Number::Integer(10))));
+    assert_eq!("2.5", format.value(&number(Number::Float(2.5))));
+}
+
+#[test]
+fn test_math_functions() {
+    use crate::number::{Angle, Mode};
+
+    let calculate = |angle: Angle, input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.angle = angle;
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    let rad = |input: &str| calculate(Angle::Rad, input);
+    let deg = |input: &str| calculate(Angle::Deg, input);
+    let float = |f: f64| Ok(Value::Number(Number::Float(f)));
+    let int = |i: i64| Ok(Value::Number(Number::Integer(i)));
+
+    assert_eq!(int(4), rad("sqrt(16)"));
+    assert_eq!(int(-3), rad("cbrt(-27)"));
+    assert_eq!(float(2f64.sqrt()), rad("sqrt(2)"));
+    assert_eq!(int(5), rad("abs(-5)"));
+    assert_eq!(int(-1), rad("sign(-2.5)"));
+    assert_eq!(float(3.0), rad("log10(1000)"));
+    assert_eq!(float(1.0), rad("ln(exp(1))"));
+    assert_eq!(float(std::f64::consts::FRAC_PI_4), rad("atan2(1, 1)"));
+
+    assert_eq!(float(-1.0), deg("cos(180)"));
+    assert_eq!(float(0.0), deg("sin(-360)"));
+    assert_eq!(float(90.0), deg("asin(1)"));
+    assert_eq!(float(1.0), calculate(Angle::Grad, "sin(100)"));
+    assert_eq!(
+        Err(SyaError::Pole("tan".to_string(), "90".to_string())),
+        deg("tan(90)")
+    );
+    assert_eq!(
+        Err(SyaError::OutOfDomain("ln".to_string(), "-1".to_string())),
+        rad("ln(-1)")
+    );
+    assert_eq!(
+        Err(SyaError::Pole("log2".to_string(), "0".to_string())),
+        rad("log2(0)")
+    );
+    assert_eq!(
+        Err(SyaError::OutOfDomain("acos".to_string(), "1.5".to_string())),
+        rad("acos(1.5)")
+    );
+    assert_eq!(
+        Err(SyaError::InvalidArguments("sin".to_string())),
+        rad("sin(1, 2)")
+    );
+
+    let mut sya = Sya::new("sqrt(2)").expect("Should Construct");
+    sya.mode = Mode::Int;
+    assert_eq!(
+        Err(SyaError::NotInteger("1.4142135623730951".to_string())),
+        sya.calculate()
+    );
+    sya.mode = Mode::Exact;
+    sya.new_input("sqrt(4 / 9) - abs(-1 / 3)")
+        .expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Rational(1, 3))), sya.out);
+}
+
+#[test]
+fn test_diff_chain_rule() {
+    use crate::number::Angle;
+
+    let diff = |angle: Angle, input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.angle = angle;
+        sya.calculate().map(|_| sya.out.unwrap().to_string())
+    };
+    assert_eq!(
+        Ok("cos(x ^ 2) * (2 * x)".to_string()),
+        diff(Angle::Rad, "diff(sin(x ^ 2), x)")
+    );
+    assert_eq!(
+        Ok("1 / x + exp(2 * x) * 2".to_string()),
+        diff(Angle::Rad, "diff(ln(x) + exp(2 * x), x)")
+    );
+    assert_eq!(
+        Ok("-sin(x) * 0.017453292519943295".to_string()),
+        diff(Angle::Deg, "diff(cos(x), x)")
+    );
+    assert_eq!(
+        Ok("1 / sqrt(1 - x ^ 2) / 0.017453292519943295".to_string()),
+        diff(Angle::Deg, "diff(asin(x), x)")
+    );
+    assert_eq!(
+        Ok("0.7390851332151607".to_string()),
+        diff(Angle::Rad, "solve(cos(x) = x, x)")
+    );
+}
+
+#[test]
+fn test_constants() {
+    use crate::number::Mode;
+
+    let mut sya = Sya::new("2 * pi - tau").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Float(0.0))), sya.out);
+
+    sya.new_input("phi ^ 2 - phi; e").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(
+        Some(Value::Number(Number::Float(std::f64::consts::E))),
+        sya.out
+    );
+
+    sya.new_input("1 / inf - nan").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert!(matches!(sya.out, Some(Value::Number(Number::Float(f))) if f.is_nan()));
+
+    sya.new_input("x = 1; pi = 3").expect("Should Parse");
+    assert_eq!(
+        Err(SyaError::Statement(
+            2,
+            Box::new(SyaError::ReadOnly("pi".to_string()))
+        )),
+        sya.calculate()
+    );
+    sya.new_input("diff(pi * x ^ 2, x)").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("pi * (2 * x)", sya.out.as_ref().unwrap().to_string());
+
+    sya.mode = Mode::Exact;
+    sya.new_input("pi").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(
+        Some(Value::Number(Number::Rational(245850922, 78256779))),
+        sya.out
+    );
+    sya.precision = Some(2);
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Rational(22, 7))), sya.out);
+    sya.precision = Some(6);
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(Some(Value::Number(Number::Rational(355, 113))), sya.out);
+}
+
+#[test]
+fn test_statistics() {
+    use crate::number::Mode;
+
+    let calculate = |mode: Mode, input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.mode = mode;
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    let auto = |input: &str| calculate(Mode::Auto, input);
+    let int = |i: i64| Ok(Value::Number(Number::Integer(i)));
+    let float = |f: f64| Ok(Value::Number(Number::Float(f)));
+
+    assert_eq!(int(10), auto("sum(1, 2, 3, 4)"));
+    assert_eq!(float(3.5), auto("sum(1, 2.5)"));
+    assert_eq!(int(3), auto("mean(2, 4)"));
+    assert_eq!(float(1.5), auto("mean(1, 2)"));
+    assert_eq!(
+        Ok(Value::Number(Number::Rational(3, 2))),
+        calculate(Mode::Exact, "mean(1, 2)")
+    );
+    assert_eq!(int(3), auto("median(5, 1, 3)"));
+    assert_eq!(float(2.5), auto("median(4, 1, 3, 2)"));
+    assert_eq!(int(1), auto("mode(3, 1, 3, 2, 1)"));
+    assert_eq!(int(-1), auto("min(3, -1, 2)"));
+    assert_eq!(float(3.5), auto("max(3, -1, 3.5)"));
+    assert_eq!(int(4), auto("varp(2, 4, 4, 4, 5, 5, 7, 9)"));
+    assert_eq!(int(2), auto("stdevp(2, 4, 4, 4, 5, 5, 7, 9)"));
+    assert_eq!(float(32.0 / 7.0), auto("var(2, 4, 4, 4, 5, 5, 7, 9)"));
+    assert_eq!(int(2), auto("percentile(25, 1, 2, 3, 4, 5)"));
+    assert_eq!(int(28), auto("percentile(90, 10, 20, 30)"));
+
+    assert_eq!(
+        Err(SyaError::InvalidArguments("sum".to_string())),
+        auto("sum()")
+    );
+    assert_eq!(
+        Err(SyaError::InvalidArguments("stdev".to_string())),
+        auto("stdev(1)")
+    );
+    assert_eq!(
+        Err(SyaError::OutOfDomain(
+            "percentile".to_string(),
+            "101".to_string()
+        )),
+        auto("percentile(101, 1)")
+    );
+}
+
+#[test]
+fn test_integer_functions() {
+    let calculate = |input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    let int = |i: i64| Ok(Value::Number(Number::Integer(i)));
+
+    assert_eq!(int(2432902008176640000), calculate("factorial(20)"));
+    assert_eq!(int(118264581564861424), calculate("nCr(60, 30)"));
+    assert_eq!(int(0), calculate("nCr(5, 7)"));
+    assert_eq!(int(720), calculate("nPr(10, 3)"));
+    assert_eq!(int(3), calculate("gcd(12, 18, 27)"));
+    assert_eq!(int(60), calculate("lcm(4, 6, 10)"));
+    assert_eq!(int(1), calculate("isprime(9223372036854775783)"));
+    assert_eq!(int(0), calculate("isprime(561)"));
+    assert_eq!(int(101), calculate("nextprime(100)"));
+    assert_eq!(int(976371285), calculate("modpow(2, 100, 1000000007)"));
+    assert_eq!(int(5), calculate("modinv(3, 7)"));
+    assert_eq!(int(97), calculate("factor(97)"));
+    assert_eq!(
+        "-1 * 2 ^ 2 * 3 * 7",
+        calculate("factor(-84)").unwrap().to_string()
+    );
+    assert_eq!(
+        "7 ^ 2 * 73 * 127 * 337 * 92737 * 649657",
+        calculate("factor(9223372036854775807)")
+            .unwrap()
+            .to_string()
+    );
+
+    // Results past i64 are big integers, which go back to i64 when they fit
+    let big = |input: &str| calculate(input).map(|v| v.to_string());
+    assert_eq!(
+        Ok("15511210043330985984000000".to_string()),
+        big("factorial(25)")
+    );
+    assert_eq!(
+        Ok("100891344545564193334812497256".to_string()),
+        big("nCr(100, 50)")
+    );
+    assert_eq!(
+        Ok("73096577329197271449600000".to_string()),
+        big("nPr(30, 20)")
+    );
+    assert_eq!(
+        Ok("50305114823068385280000".to_string()),
+        big("lcm(factorial(20), 17, 19, 23, 29, 31)")
+    );
+    assert_eq!(
+        Ok("-15511210043330985983999999".to_string()),
+        big("1 - factorial(25)")
+    );
+    assert_eq!(int(600), calculate("factorial(25) / factorial(23)"));
+    assert!(matches!(
+        calculate("factorial(25) / 29"),
+        Ok(Value::Number(Number::Float(f))) if (f / 5.348693118389995e23 - 1.0).abs() < 1e-12
+    ));
+    assert_eq!(
+        Err(SyaError::NumberOverflow(
+            "1124000727777607680000 ^ 20000".to_string()
+        )),
+        calculate("factorial(22) ^ 20000")
+    );
+    assert_eq!(
+        Err(SyaError::NumberOverflow(
+            "9223372036854775807 + 1".to_string()
+        )),
+        calculate("9223372036854775807 + 1")
+    );
+    assert_eq!(
+        Err(SyaError::OutOfDomain(
+            "modinv".to_string(),
+            "4, 6".to_string()
+        )),
+        calculate("modinv(4, 6)")
+    );
+    assert_eq!(
+        Err(SyaError::NotInteger("2.5".to_string())),
+        calculate("factorial(2.5)")
+    );
+}
+
+#[test]
+fn test_rounding() {
+    use crate::number::Rounding;
+
+    let calculate = |rounding: Rounding, input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.rounding = rounding;
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    let float = |f: f64| Ok(Value::Number(Number::Float(f)));
+    let int = |i: i64| Ok(Value::Number(Number::Integer(i)));
+
+    let ties = "round(2.5); round(-2.5)";
+    let cases = [
+        (Rounding::HalfAway, [3.0, -3.0, 2.68, 0.13]),
+        (Rounding::HalfUp, [3.0, -2.0, 2.68, 0.13]),
+        (Rounding::HalfEven, [2.0, -2.0, 2.68, 0.12]),
+        (Rounding::TowardZero, [2.0, -2.0, 2.67, 0.12]),
+    ];
+    for (rounding, [pos, neg, cents, even]) in cases {
+        let mut sya = Sya::new(ties).expect("Should Construct");
+        sya.rounding = rounding;
+        assert_eq!(Ok(()), sya.calculate());
+        assert_eq!(
+            &Value::Number(Number::Float(neg)),
+            sya.results.last().unwrap()
+        );
+        assert_eq!(float(pos), calculate(rounding, "round(2.5)"));
+        assert_eq!(float(cents), calculate(rounding, "round(2.675, 2)"));
+        assert_eq!(float(even), calculate(rounding, "round(0.125, 2)"));
+    }
+    let rounding = Rounding::default();
+    assert_eq!(int(1300), calculate(rounding, "round(1250, -2)"));
+    assert_eq!(int(1200), calculate(Rounding::HalfEven, "round(1250, -2)"));
+    assert_eq!(int(15), calculate(rounding, "roundto(17, 5)"));
+    assert_eq!(float(0.3), calculate(rounding, "roundto(0.31, 0.1)"));
+    assert_eq!(float(-3.0), calculate(rounding, "floor(-5 / 2)"));
+    assert_eq!(float(3.0), calculate(rounding, "ceil(2.1)"));
+    assert_eq!(float(-2.0), calculate(rounding, "trunc(-2.7)"));
+    assert_eq!(float(0.7), calculate(rounding, "frac(2.7)"));
+    assert_eq!(int(0), calculate(rounding, "frac(-7)"));
+    assert_eq!(
+        Err(SyaError::DivisionByZero),
+        calculate(rounding, "roundto(1, 0)")
+    );
+    assert_eq!(
+        Err(SyaError::NotInteger("0.5".to_string())),
+        calculate(rounding, "round(2, 0.5)")
+    );
+}
+
+#[test]
+fn test_lists() {
+    let calculate = |input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    let int = |i: i64| Value::Number(Number::Integer(i));
+    let list = |items: &[i64]| Ok(Value::List(items.iter().map(|&i| int(i)).collect()));
+
+    let mut sya = Sya::new("[1, 2] * 3").expect("Should Construct");
+    assert_eq!(
+        vec![
+            Token::LBRACKET,
+            Token::Number(Number::Integer(1)),
+            Token::COMMA,
+            Token::Number(Number::Integer(2)),
+            Token::RBRACKET,
+        ],
+        sya.statements[0][..5]
+    );
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(sya.rpn_formatted(), "1 2 list 3 *");
+    assert_eq!(list(&[3, 6]), Ok(sya.out.unwrap()));
+
+    assert_eq!(list(&[11, 22, 33]), calculate("[1, 2, 3] + [10, 20, 30]"));
+    assert_eq!(list(&[2, 4, 8]), calculate("2 ^ [1, 2, 3]"));
+    assert_eq!(list(&[-1, -2]), calculate("-[1, 2]"));
+    assert_eq!(
+        list(&[2, 5, 10]),
+        calculate("f(x) = x ^ 2 + 1; f([1, 2, 3])")
+    );
+    assert_eq!(list(&[]), calculate("[]"));
+    assert_eq!(Ok(int(5)), calculate("v = [5, 6, 7, 8]; v[0]"));
+    assert_eq!(Ok(int(8)), calculate("v = [5, 6, 7, 8]; v[-1]"));
+    assert_eq!(list(&[6, 7]), calculate("v = [5, 6, 7, 8]; v[1:3]"));
+    assert_eq!(list(&[7, 8]), calculate("v = [5, 6, 7, 8]; v[2:]"));
+    assert_eq!(list(&[5, 6]), calculate("v = [5, 6, 7, 8]; v[:-2]"));
+    assert_eq!(Ok(int(3)), calculate("[[1, 2], [3, 4]][1][0]"));
+    assert_eq!(Ok(int(4)), calculate("(2 * [1, 2])[1]"));
+    assert_eq!(Ok(int(3)), calculate("len([1, 2, 3])"));
+    assert_eq!(Ok(int(32)), calculate("dot([1, 2, 3], [4, 5, 6])"));
+    assert_eq!(Ok(int(10)), calculate("sum([1, 2], [3, 4])"));
+    assert_eq!(
+        Ok(Value::List(vec![int(2), Value::Number(Number::Float(2.5))])),
+        calculate("sqrt([4, 6.25])")
+    );
+    assert_eq!(
+        "x[0] + (-x)[1:] * [x, 1][0:2]",
+        calculate("g(x) = x[0] + (-x)[1:] * [x, 1][:2]")
+            .unwrap()
+            .to_string()
+    );
+
+    assert_eq!(
+        Err(SyaError::LengthMismatch(2, 3)),
+        calculate("[1, 2] + [1, 2, 3]")
+    );
+    assert_eq!(
+        Err(SyaError::LengthMismatch(2, 1)),
+        calculate("dot([1, 2], [1])")
+    );
+    assert_eq!(
+        Err(SyaError::IndexOutOfRange(4, 4)),
+        calculate("[5, 6, 7, 8][4]")
+    );
+    assert_eq!(
+        Err(SyaError::ExpectedList("5".to_string())),
+        calculate("len(5)")
+    );
+    assert_eq!(
+        Err(SyaError::ExpectedNumber("[0, -1]".to_string())),
+        calculate("solve(x = [1, 2], x)")
+    );
+    assert_eq!(
+        Err(SyaError::ExpectedChar('[')),
+        Sya::new("1 + 2]").and_then(|mut s| s.calculate())
+    );
+}
+
+#[test]
+fn test_matrices() {
+    use crate::number::Mode;
+    let calculate = |input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.calculate().map(|_| sya.out.unwrap())
+    };
+    let number = |n: f64| match n.fract() {
+        0.0 => Value::Number(Number::Integer(n as i64)),
+        _ => Value::Number(Number::Float(n)),
+    };
+    let row = |items: &[f64]| Value::List(items.iter().map(|&n| number(n)).collect());
+    let matrix = |rows: &[&[f64]]| Ok(Value::List(rows.iter().map(|r| row(r)).collect()));
+
+    assert_eq!(
+        matrix(&[&[4.0, 1.0], &[11.0, 3.0]]),
+        calculate("A = [[2, 1], [5, 3]]; matmul(A, [[1, 0], [2, 1]])")
+    );
+    assert_eq!(
+        matrix(&[&[7.0, 10.0], &[15.0, 22.0]]),
+        calculate("A = [[1, 2], [3, 4]]; A * A")
+    );
+    assert_eq!(
+        matrix(&[&[2.0, 4.0], &[6.0, 8.0]]),
+        calculate("[[1, 2], [3, 4]] * 2")
+    );
+    assert_eq!(
+        Ok(row(&[4.0, 11.0])),
+        calculate("matmul([[2, 1], [5, 3]], [1, 2])")
+    );
+    assert_eq!(
+        Ok(row(&[12.0, 7.0])),
+        calculate("matmul([1, 2], [[2, 1], [5, 3]])")
+    );
+    assert_eq!(
+        matrix(&[&[1.0, 4.0], &[2.0, 5.0], &[3.0, 6.0]]),
+        calculate("transpose([[1, 2, 3], [4, 5, 6]])")
+    );
+    assert_eq!(
+        matrix(&[&[1.0, 0.0], &[0.0, 1.0]]),
+        calculate("identity(2)")
+    );
+    assert_eq!(Ok(number(-2.0)), calculate("det([[1, 2], [3, 4]])"));
+    assert_eq!(
+        Ok(number(-306.0)),
+        calculate("det([[6, 1, 1], [4, -2, 5], [2, 8, 7]])")
+    );
+    assert_eq!(Ok(number(0.0)), calculate("det([[1, 2], [2, 4]])"));
+    assert_eq!(
+        matrix(&[&[-2.0, 1.0], &[1.5, -0.5]]),
+        calculate("inv([[1, 2], [3, 4]])")
+    );
+    assert_eq!(
+        Ok(row(&[0.8, 1.4])),
+        calculate("linsolve([[2, 1], [1, 3]], [3, 5])")
+    );
+    assert_eq!(
+        matrix(&[&[3.0, 4.0], &[1.0, 2.0]]),
+        calculate("linsolve([[0, 1], [1, 0]], [[1, 2], [3, 4]])")
+    );
+    let mut sya = Sya::new("inv([[1, 2], [3, 4]])").expect("Should Construct");
+    sya.mode = Mode::Exact;
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("[[-2, 1], [3/2, -1/2]]", sya.out.unwrap().to_string());
+
+    assert_eq!(
+        Err(SyaError::DimensionMismatch((1, 3), (1, 2))),
+        calculate("matmul([[1, 2, 3]], [[1, 2]])")
+    );
+    assert_eq!(
+        Err(SyaError::DimensionMismatch((2, 2), (3, 1))),
+        calculate("linsolve([[1, 2], [3, 4]], [1, 2, 3])")
+    );
+    assert_eq!(
+        Err(SyaError::NotSquare(2, 3)),
+        calculate("det([[1, 2, 3], [4, 5, 6]])")
+    );
+    assert_eq!(Err(SyaError::Singular), calculate("inv([[1, 2], [2, 4]])"));
+    assert_eq!(
+        Err(SyaError::ExpectedMatrix("[[1, 2], [3]]".to_string())),
+        calculate("det([[1, 2], [3]])")
+    );
+    assert_eq!(
+        Err(SyaError::DimensionMismatch((1, 3), (2, 1))),
+        calculate("[[1, 2, 3]] * [[1], [2]]")
+    );
+    assert_eq!(
+        Err(SyaError::MatrixTooLarge(100000)),
+        calculate("identity(100000)")
+    );
+}
+
+#[test]
+fn test_units() {
+    use crate::{
+        number::Mode,
+        units::{self, Unit, Units},
+    };
+    let calculate = |input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.calculate().map(|_| sya.out.unwrap().to_string())
+    };
+    let unit = |terms: &[(&str, i32)]| Unit {
+        terms: terms.iter().map(|(n, p)| (n.to_string(), *p)).collect(),
+    };
+
+    let mut sya = Sya::new("9.81 m/s^2 * 2 s to km/h").expect("Should Construct");
+    assert_eq!(
+        vec![
+            Token::Number(Number::Float(9.81)),
+            Token::Unit(unit(&[("m", 1), ("s", -2)])),
+            Token::Operator(op('*', Precedence::MUL)),
+            Token::Number(Number::Integer(2)),
+            Token::Unit(unit(&[("s", 1)])),
+            Token::Convert(unit(&[("km", 1), ("h", -1)])),
+        ],
+        sya.statements[0]
+    );
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(sya.rpn_formatted(), "9.81 m/s^2 2 s * to km/h");
+
+    let ok = |s: &str| Ok(s.to_string());
+    assert_eq!(ok("3.2 m"), calculate("3 m + 20 cm"));
+    assert_eq!(ok("19.62 m/s"), calculate("9.81 m/s^2 * 2 s"));
+    assert_eq!(ok("16.666666666666668 m/s"), calculate("60 km/h to m/s"));
+    assert_eq!(ok("1000 m"), calculate("1 km in m"));
+    assert_eq!(ok("1.609344 km/h"), calculate("1 mph to km/h"));
+    assert_eq!(ok("5 m/s"), calculate("10 m / 2 s"));
+    assert_eq!(ok("4 m^2"), calculate("(2 m) ^ 2"));
+    assert_eq!(ok("0.5 s^-1"), calculate("1 / (2 s)"));
+    assert_eq!(ok("1000"), calculate("1 km / 1 m"));
+    assert_eq!(ok("[1 m, 2 m]"), calculate("[100, 200] cm to m"));
+    assert_eq!(ok("6 km"), calculate("d = 3 km; d * 2"));
+    assert_eq!(ok("6 m"), calculate("t = 2; 3 m * t"));
+    assert_eq!(ok("(x to km) + 2 m"), calculate("f(x) = x to km + 2 m"));
+    assert_eq!(ok("0.5 km"), calculate("f(x) = x to km; f(500 m)"));
+
+    let mut sya = Sya::new("3 m + 20 cm").expect("Should Construct");
+    sya.mode = Mode::Exact;
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("16/5 m", sya.out.unwrap().to_string());
+
+    // Units are known to the tokenizer, so they're set before the input
+    let mut sya = Sya::new("").expect("Should Construct");
+    sya.units = units::parse("# Horse racing\nfurlong = 201.168 m").expect("Should Parse");
+    sya.new_input("2 furlong to m").expect("Should Parse");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!("402.336 m", sya.out.unwrap().to_string());
+    assert_eq!(
+        Err("2: Unknown unit 'parsec'".to_string()),
+        units::parse("furlong = 201.168 m\nlight = 3 parsec").map(|_| ())
+    );
+
+    assert_eq!(
+        Err(SyaError::IncompatibleUnits(
+            "m".to_string(),
+            "s".to_string()
+        )),
+        calculate("3 m + 2 s")
+    );
+    assert_eq!(
+        Err(SyaError::IncompatibleUnits(
+            "1".to_string(),
+            "m".to_string()
+        )),
+        calculate("2 + 3 m")
+    );
+    assert_eq!(
+        Err(SyaError::IncompatibleUnits(
+            "km/h".to_string(),
+            "kg".to_string()
+        )),
+        calculate("60 km/h to kg")
+    );
+    assert_eq!(
+        Err(SyaError::UnitPower("0.5".to_string())),
+        calculate("(1 m) ^ 0.5")
+    );
+    assert_eq!(
+        Err(SyaError::UnitPower("0.5".to_string())),
+        calculate("5 m ^ 0.5")
+    );
+    assert_eq!(
+        Err(SyaError::UnknownUnit("parsec".to_string())),
+        calculate("3 m to parsec")
+    );
+
+    // Names after a number that aren't units are left to the parser
+    assert_eq!(
+        Err(SyaError::UnknownVariable("parsec".to_string())),
+        calculate("3 parsec")
+    );
+    assert_eq!(ok("1 km"), calculate("x = 2; 2 km/x"));
+    let builtin = Units::default();
+    let mut tokenizer = Tokenizer::new("2 x").with_units(&builtin);
+    assert_eq!(
+        Ok(&vec![
+            Token::Number(Number::Integer(2)),
+            Token::Ident("x".to_string())
+        ]),
+        tokenizer.parse()
+    );
+
+    // Exponents aren't units either
+    assert_eq!(ok("10"), calculate("solve(x ^ 3 = 1e3, x)"));
+    assert_eq!(ok("100000"), calculate("root(x ^ 2 - 1e10, 1)"));
+    assert_eq!(ok("0.0025 m"), calculate("2.5e-3 m"));
+    assert_eq!(ok("100"), calculate("1E+2"));
+    assert_eq!(
+        Err(SyaError::NumberOverflow("1e400".to_string())),
+        Sya::new("1e400").map(|_| ())
+    );
+}
+
+#[test]
+fn test_data_units() {
+    let calculate = |input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.calculate().map(|_| sya.out.unwrap().to_string())
+    };
+    let ok = |s: &str| Ok(s.to_string());
+
+    let mut sya = Sya::new("2 * 3h 20min + 45min").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(sya.rpn_formatted(), "2 3 h 20 min + * 45 min +");
+    assert_eq!("7 h 25 min", sya.out.unwrap().to_string());
+
+    assert_eq!(ok("384 MiB"), calculate("1.5 GiB / 4"));
+    assert_eq!(ok("476.837158203125 MiB"), calculate("500 MB to MiB"));
+    assert_eq!(ok("4 h 5 min"), calculate("3h 20min + 45min"));
+    assert_eq!(ok("13 min 20 s"), calculate("10 GB / 100 Mbit/s"));
+    assert_eq!(ok("1.5 GB"), calculate("1500 MB"));
+    assert_eq!(ok("1.024 kB"), calculate("1024 B"));
+    assert_eq!(ok("1 MiB"), calculate("512 KiB * 2"));
+    assert_eq!(ok("1.024 kB"), calculate("1 KiB to kB"));
+    assert_eq!(ok("360 Gbit"), calculate("100 Mbit/s * 1 h"));
+    assert_eq!(ok("12.5 MB/s"), calculate("100 Mbit/s to MB/s"));
+    assert_eq!(ok("500 ms"), calculate("0.5 s"));
+    assert_eq!(ok("3600 s"), calculate("1 h to s"));
+    assert_eq!(ok("2500 m"), calculate("2500 m"));
+    assert_eq!(ok("1 h 30 min"), calculate("1 h 30 min"));
+    // Only durations are summed, and only with another duration
+    assert_eq!(Err(SyaError::InvalidInput), calculate("5 kg 3"));
+    assert_eq!(Err(SyaError::InvalidInput), calculate("1 h 3"));
+    assert_eq!(Err(SyaError::InvalidInput), calculate("2 m 3 m"));
+    assert_eq!(
+        Err(SyaError::UnknownUnit("Kim".to_string())),
+        calculate("1 KiB to Kim")
+    );
+}
+
+#[test]
+fn test_dates() {
+    let calculate = |input: &str| {
+        let mut sya = Sya::new(input).expect("Should Construct");
+        sya.calculate().map(|_| sya.out.unwrap().to_string())
+    };
+    let ok = |s: &str| Ok(s.to_string());
+
+    let mut sya = Sya::new("2024-05-20T10:00 + 30d").expect("Should Construct");
+    assert_eq!(Ok(()), sya.calculate());
+    assert_eq!(sya.rpn_formatted(), "2024-05-20T10:00 30 d +");
+    assert_eq!("2024-06-19T10:00", sya.out.unwrap().to_string());
+
+    assert_eq!(ok("2024-06-19"), calculate("2024-05-20 + 30d"));
+    assert_eq!(ok("2024-05-06"), calculate("2024-05-20 - 2w"));
+    assert_eq!(ok("12 d"), calculate("2024-06-01 - 2024-05-20"));
+    assert_eq!(ok("2 d 10 h"), calculate("2024-05-20T10:00 - 2024-05-18"));
+    assert_eq!(
+        ok("2025-01-01T00:00:15"),
+        calculate("2024-12-31T23:59:30 + 45 s")
+    );
+    assert_eq!(ok("2024-03-01"), calculate("2024-02-28 + 2d"));
+    assert_eq!(
+        ok("2024-05-21T00:30+02:00"),
+        calculate("2024-05-20T23:30+02:00 + 1h")
+    );
+    assert_eq!(
+        ok("3 h"),
+        calculate("2024-05-20T10:00Z - 2024-05-20T09:00+02:00")
+    );
+    assert_eq!(ok("1"), calculate("weekday(2024-05-20)"));
+    assert_eq!(ok("7"), calculate("weekday(2024-05-26T23:00-05:00)"));
+    assert_eq!(ok("2024-02-14"), calculate("f(d) = d + 30d; f(2024-01-15)"));
+    assert_eq!(ok("1999"), calculate("2024 - 05 - 20"));
+
+    assert_eq!(
+        Err(SyaError::InvalidDate("2023-02-29".to_string())),
+        Sya::new("2023-02-29").map(|_| ())
+    );
+    assert_eq!(
+        Err(SyaError::ExpectedDuration("3".to_string())),
+        calculate("2024-05-20 + 3")
+    );
+    assert_eq!(
+        Err(SyaError::InvalidOperation('*')),
+        calculate("2024-05-20 * 2")
+    );
+    assert_eq!(
+        Err(SyaError::ExpectedDate("3".to_string())),
+        calculate("weekday(3)")
+    );
+}
@@ -1,6 +1,11 @@
+use core::fmt;
+
+use crate::dates::{self, Date};
 use crate::errors::SyaError;
 use crate::number::Number;
+use crate::units::{Unit, Units};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub enum Precedence {
     MIN,
@@ -16,45 +21,99 @@ pub struct Operator {
     pub precedence: Precedence,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Number(Number),
+    Ident(String),
+    Function(String),
+    Call(String, usize),
     Operator(Operator),
+    ASSIGN,
+    COMMA,
     UNARY(char),
     OPEN,
     CLOSE,
+    /// `[`, starting a list or, after a value, an index or slice.
+    LBRACKET,
+    RBRACKET,
+    /// Separates the bounds of a slice.
+    COLON,
+    /// A unit right after a number, `)` or `]`, like `km/h` in `60 km/h`.
+    Unit(Unit),
+    /// `to` or `in` with the unit to convert to.
+    Convert(Unit),
+    /// An ISO date like `2024-05-20`, with an optional time and offset.
+    Date(Date),
 }
 impl Token {
     pub fn precedence(&self) -> Option<&Precedence> {
         match self {
             Token::Operator(o) => Some(&o.precedence),
-            Token::UNARY(_) | Token::CLOSE => Some(&Precedence::MAX),
-            Token::OPEN => Some(&Precedence::MIN),
+            Token::UNARY(_) | Token::Function(_) | Token::CLOSE => Some(&Precedence::MAX),
+            Token::OPEN | Token::LBRACKET => Some(&Precedence::MIN),
             _ => None,
         }
     }
 }
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Ident(i) | Token::Function(i) | Token::Call(i, _) => write!(f, "{}", i),
+            Token::ASSIGN => write!(f, "="),
+            Token::COMMA => write!(f, ","),
+            Token::Operator(o) => write!(f, "{}", o.sign),
+            Token::UNARY(s) => write!(f, "u{}", s),
+            Token::OPEN => write!(f, "("),
+            Token::CLOSE => write!(f, ")"),
+            Token::LBRACKET => write!(f, "["),
+            Token::RBRACKET => write!(f, "]"),
+            Token::COLON => write!(f, ":"),
+            Token::Unit(u) => write!(f, "{}", u),
+            Token::Convert(u) => write!(f, "to {}", u),
+            Token::Date(d) => write!(f, "{}", d),
+        }
+    }
+}
 
-pub struct Tokenizer {
+pub struct Tokenizer<'a> {
     tokens: Vec<Token>,
+    /// Character range of each token.
+    spans: Vec<(usize, usize)>,
     input: String,
+    /// Where the current token starts.
+    start: usize,
     position: usize,
     read_position: usize,
     ch: char,
+    /// Names a unit after a number can have. Without them any name is taken
+    /// as a unit, to be checked when it's measured.
+    units: Option<&'a Units>,
 }
-impl Tokenizer {
-    pub fn new(input: &str) -> Tokenizer {
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &str) -> Tokenizer<'a> {
         let mut t = Tokenizer {
             tokens: Vec::new(),
+            spans: Vec::new(),
             input: input.to_owned(),
+            start: 0,
             position: 0,
             read_position: 0,
             ch: '\0',
+            units: None,
         };
         t.read();
         t
     }
 
+    /// Only reads the names in `units` as units after a number, so `2 x` is
+    /// an error rather than an unknown unit.
+    pub fn with_units(mut self, units: &'a Units) -> Tokenizer<'a> {
+        self.units = Some(units);
+        self
+    }
+
     fn read(&mut self) {
         if self.read_position >= self.input.len() {
             self.ch = '\0';
@@ -67,7 +126,31 @@ impl Tokenizer {
 
     pub fn parse(&mut self) -> Result<&Vec<Token>, SyaError> {
         while self.ch != '\0' {
+            self.close_span();
             self.skip_space();
+            if self.ch == '\0' {
+                break;
+            }
+            self.start = self.position;
+            // `3h 20min` is a single duration, so the sum binds tightest.
+            if self.ch.is_ascii_digit()
+                && matches!(
+                    self.tokens.as_slice(),
+                    [.., Token::Number(_), Token::Unit(unit)] if unit.is_duration()
+                )
+                && self.duration_follows()
+            {
+                self.tokens.push(self.op_token('+', Precedence::MAX));
+            }
+            if matches!(
+                self.tokens.last(),
+                Some(Token::Number(_) | Token::CLOSE | Token::RBRACKET)
+            ) {
+                if let Some(unit) = self.read_unit(true) {
+                    self.tokens.push(Token::Unit(unit));
+                    continue;
+                }
+            }
             match self.ch {
                 '(' => self.tokens.push(Token::OPEN),
                 '^' => self.tokens.push(self.op_token(self.ch, Precedence::EXP)),
@@ -82,10 +165,46 @@ impl Tokenizer {
                     self.tokens.push(token)
                 }
                 ')' => self.tokens.push(Token::CLOSE),
+                '[' => self.tokens.push(Token::LBRACKET),
+                ']' => self.tokens.push(Token::RBRACKET),
+                ':' => self.tokens.push(Token::COLON),
+                '=' => self.tokens.push(Token::ASSIGN),
+                ',' => self.tokens.push(Token::COMMA),
+                // Comments run to the end of the input.
+                '#' => break,
+                '$' if self.peek().is_ascii_digit() => {
+                    let reference = self.read_reference();
+                    self.tokens.push(Token::Ident(reference));
+                    continue;
+                }
                 _ => {
-                    if !self.ch.is_digit(10) {
+                    if self.ch.is_ascii_alphabetic() || self.ch == '_' {
+                        let ident = self.read_ident();
+                        if matches!(ident.as_str(), "to" | "in") && self.follows_value() {
+                            if let Some(unit) = self.read_target() {
+                                self.tokens.push(Token::Convert(unit));
+                                continue;
+                            }
+                        }
+                        if self.ch == '(' {
+                            self.tokens.push(Token::Function(ident));
+                        } else {
+                            self.tokens.push(Token::Ident(ident));
+                        }
+                        continue;
+                    }
+                    if !self.ch.is_ascii_digit() {
                         return Err(SyaError::InvalidChar(self.ch));
                     }
+                    let rest: Vec<char> = self.input.chars().skip(self.position).collect();
+                    if let Some(date) = dates::read(&rest) {
+                        let (date, len) = date?;
+                        for _ in 0..len {
+                            self.read();
+                        }
+                        self.tokens.push(Token::Date(date));
+                        continue;
+                    }
                     let number = self.read_number()?;
                     self.tokens.push(Token::Number(number));
                     continue;
@@ -93,31 +212,82 @@ impl Tokenizer {
             }
             self.read();
         }
+        self.close_span();
         Ok(&self.tokens)
     }
 
+    /// Every token read so far with its character range, also after an error.
+    pub fn spanned(&self) -> impl Iterator<Item = (&Token, (usize, usize))> {
+        self.tokens.iter().zip(self.spans.iter().copied())
+    }
+
+    fn close_span(&mut self) {
+        if self.spans.len() < self.tokens.len() {
+            self.spans.push((self.start, self.position));
+        }
+    }
+
+    /// Character range of the last token read, the one that failed after an
+    /// error.
+    pub fn span(&self) -> (usize, usize) {
+        (self.start, self.position.max(self.start + 1))
+    }
+
     fn handle_unary(&mut self, precedence: Precedence) -> Token {
-        match self.tokens.last() {
-            Some(Token::CLOSE) | Some(Token::Number(_)) => self.op_token(self.ch, precedence),
-            _ => Token::UNARY(self.ch),
+        match self.follows_value() {
+            true => self.op_token(self.ch, precedence),
+            false => Token::UNARY(self.ch),
         }
     }
 
+    fn follows_value(&self) -> bool {
+        matches!(
+            self.tokens.last(),
+            Some(
+                Token::CLOSE
+                    | Token::RBRACKET
+                    | Token::Number(_)
+                    | Token::Date(_)
+                    | Token::Ident(_)
+                    | Token::Unit(_)
+                    | Token::Convert(_)
+            )
+        )
+    }
+
     fn op_token(&self, sign: char, precedence: Precedence) -> Token {
         Token::Operator(Operator { sign, precedence })
     }
 
+    /// Reads a number like `12`, `1.5` or `1e-3`. Numbers with a decimal
+    /// point or an exponent are floats.
     fn read_number(&mut self) -> Result<Number, SyaError> {
         let pos = self.position;
-        while self.ch.is_digit(10) || self.ch == '.' {
+        while self.ch.is_ascii_digit() || self.ch == '.' {
+            self.read();
+        }
+        let exponent = matches!(self.ch, 'e' | 'E')
+            && match self.peek() {
+                '+' | '-' => self
+                    .input
+                    .chars()
+                    .nth(self.read_position + 1)
+                    .is_some_and(|c| c.is_ascii_digit()),
+                c => c.is_ascii_digit(),
+            };
+        if exponent {
+            self.read();
             self.read();
+            while self.ch.is_ascii_digit() {
+                self.read();
+            }
         }
 
         let n = &self.input[pos..self.position];
-        if n.contains('.') {
+        if exponent || n.contains('.') {
             let parsed = match n.parse::<f64>() {
-                Ok(f) => f,
-                Err(_) => return Err(SyaError::NumberOverflow(n.to_string())),
+                Ok(f) if f.is_finite() => f,
+                _ => return Err(SyaError::NumberOverflow(n.to_string())),
             };
             Ok(Number::Float(parsed))
         } else {
@@ -129,6 +299,121 @@ impl Tokenizer {
         }
     }
 
+    fn read_ident(&mut self) -> String {
+        let pos = self.position;
+        while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+            self.read();
+        }
+        self.input[pos..self.position].to_string()
+    }
+
+    /// Reads the unit after `to` or `in`, leaving the position as it was when
+    /// there's none.
+    fn read_target(&mut self) -> Option<Unit> {
+        let mark = self.mark();
+        self.skip_space();
+        let unit = self.read_unit(false);
+        if unit.is_none() {
+            self.reset(mark);
+        }
+        unit
+    }
+
+    /// Reads a unit like `km/h` or `m/s^2`, written without spaces, if one
+    /// starts at the current character. Names followed by `(` are functions,
+    /// and when `known` only names of `units` are read, so `2 km/x` is `2 km`
+    /// divided by `x`.
+    fn read_unit(&mut self, known: bool) -> Option<Unit> {
+        let mut terms = Vec::new();
+        let mut sign = 1;
+        // Where the unit ends so far, before a `*` or `/` that may not belong
+        // to it.
+        let mut end = self.mark();
+        loop {
+            let name = match self.ch.is_ascii_alphabetic() {
+                true => self.read_ident(),
+                false => String::new(),
+            };
+            let unknown = known && self.units.is_some_and(|units| !units.contains(&name));
+            if name.is_empty() || unknown || self.ch == '(' || matches!(name.as_str(), "to" | "in")
+            {
+                self.reset(end);
+                break;
+            }
+            let power = self.read_power();
+            terms.push((name, power * sign));
+            end = self.mark();
+            match self.ch {
+                '*' | '/' if self.peek().is_ascii_alphabetic() => {
+                    sign = if self.ch == '*' { 1 } else { -1 };
+                    self.read();
+                }
+                _ => break,
+            }
+        }
+        (!terms.is_empty()).then_some(Unit { terms })
+    }
+
+    /// Whether the number at the current character is followed by a duration
+    /// unit, like `30 min`, leaving the position as it was.
+    fn duration_follows(&mut self) -> bool {
+        let mark = self.mark();
+        let follows = self.read_number().is_ok() && {
+            self.skip_space();
+            self.read_unit(true).is_some_and(|unit| unit.is_duration())
+        };
+        self.reset(mark);
+        follows
+    }
+
+    /// Reads a power like `^2` or `^-1` after a unit name, 1 when there's
+    /// none.
+    fn read_power(&mut self) -> i32 {
+        if self.ch != '^' {
+            return 1;
+        }
+        let mark = self.mark();
+        self.read();
+        let negative = self.ch == '-';
+        if negative {
+            self.read();
+        }
+        let start = self.position;
+        while self.ch.is_ascii_digit() {
+            self.read();
+        }
+        match self.input[start..self.position].parse::<i32>() {
+            Ok(p) if negative => -p,
+            Ok(p) => p,
+            Err(_) => {
+                self.reset(mark);
+                1
+            }
+        }
+    }
+
+    fn mark(&self) -> (usize, usize, char) {
+        (self.position, self.read_position, self.ch)
+    }
+
+    fn reset(&mut self, mark: (usize, usize, char)) {
+        (self.position, self.read_position, self.ch) = mark;
+    }
+
+    /// Reads a reference to a previous result, like `$2`.
+    fn read_reference(&mut self) -> String {
+        let pos = self.position;
+        self.read();
+        while self.ch.is_ascii_digit() {
+            self.read();
+        }
+        self.input[pos..self.position].to_string()
+    }
+
+    fn peek(&self) -> char {
+        self.input.chars().nth(self.read_position).unwrap_or('\0')
+    }
+
     fn skip_space(&mut self) {
         while self.ch.is_whitespace() {
             self.read();
@@ -0,0 +1,112 @@
+use crate::{tokenizer::Token, value::Value};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RpnStep {
+    pub token: Token,
+    pub output: Vec<Token>,
+    pub holding: Vec<Token>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct EvalStep {
+    pub token: Token,
+    pub before: Vec<Value>,
+    pub after: Vec<Value>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Trace {
+    pub rpn: Vec<RpnStep>,
+    pub eval: Vec<EvalStep>,
+    /// Index of the first RPN and evaluation step of each statement.
+    starts: Vec<(usize, usize)>,
+}
+impl Trace {
+    pub fn clear(&mut self) {
+        self.rpn.clear();
+        self.eval.clear();
+        self.starts.clear();
+    }
+
+    /// Marks where the steps of the next statement start.
+    pub fn begin(&mut self) {
+        self.starts.push((self.rpn.len(), self.eval.len()));
+    }
+
+    /// The RPN and evaluation tables of each statement, under a header when
+    /// there are several.
+    pub fn tables(&self) -> String {
+        let mut starts = self.starts.clone();
+        if starts.first() != Some(&(0, 0)) {
+            starts.insert(0, (0, 0));
+        }
+        let mut sections = Vec::new();
+        for (i, &(rpn, eval)) in starts.iter().enumerate() {
+            let (rpn_end, eval_end) = starts
+                .get(i + 1)
+                .copied()
+                .unwrap_or((self.rpn.len(), self.eval.len()));
+            if starts.len() > 1 {
+                sections.push(format!("Statement {}", i + 1));
+            }
+            sections.push(rpn_table(&self.rpn[rpn..rpn_end]));
+            sections.push(eval_table(&self.eval[eval..eval_end]));
+        }
+        sections.join("\n\n")
+    }
+}
+
+fn rpn_table(steps: &[RpnStep]) -> String {
+    let rows = steps
+        .iter()
+        .map(|s| vec![s.token.to_string(), join(&s.output), join(&s.holding)])
+        .collect();
+    table(&["Token", "Output Queue", "Holding Stack"], rows)
+}
+
+fn eval_table(steps: &[EvalStep]) -> String {
+    let rows = steps
+        .iter()
+        .map(|s| vec![s.token.to_string(), join(&s.before), join(&s.after)])
+        .collect();
+    table(&["Token", "Stack Before", "Stack After"], rows)
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{:<w$}", c, w = w))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut out = vec![line(header.iter().map(|h| h.to_string()).collect())];
+    out.push(
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .join("-+-"),
+    );
+    out.extend(rows.into_iter().map(line));
+    out.join("\n")
+}
@@ -0,0 +1,568 @@
+use core::fmt;
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use crate::{
+    config::config_dir,
+    errors::SyaError,
+    expr::Expr,
+    number::{Mode, Number},
+    tokenizer::{Token, Tokenizer},
+    value::Value,
+};
+
+/// Powers of the SI base units: metre, kilogram, second, ampere, kelvin,
+/// mole and candela, and of bits of information.
+pub type Dimension = [i32; 8];
+
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0, 0];
+const DATA: Dimension = [0, 0, 0, 0, 0, 0, 0, 1];
+const RATE: Dimension = [0, 0, -1, 0, 0, 0, 0, 1];
+
+/// SI base units in the order of `Dimension`. The kilogram is a gram with a
+/// prefix.
+const BASE: [(&str, f64); 8] = [
+    ("m", 1.0),
+    ("g", 0.001),
+    ("s", 1.0),
+    ("A", 1.0),
+    ("K", 1.0),
+    ("mol", 1.0),
+    ("cd", 1.0),
+    ("bit", 1.0),
+];
+
+/// Built-in units, in the format of a units file.
+const DEFINITIONS: &str = "
+Hz = 1 s^-1
+N = 1 kg*m/s^2
+Pa = 1 N/m^2
+J = 1 N*m
+W = 1 J/s
+C = 1 A*s
+V = 1 W/A
+ohm = 1 V/A
+L = 1 dm^3
+t = 1000 kg
+min = 60 s
+h = 60 min
+d = 24 h
+week = 7 d
+w = 1 week
+inch = 2.54 cm
+ft = 12 inch
+yd = 3 ft
+mi = 1760 yd
+lb = 0.45359237 kg
+oz = 0.0625 lb
+mph = 1 mi/h
+bar = 100000 Pa
+atm = 101325 Pa
+cal = 4.184 J
+Wh = 3600 J
+B = 8 bit
+";
+
+/// Prefixes any unit takes, `da` before `d` so it's matched first.
+const PREFIXES: &[(&str, f64)] = &[
+    ("da", 1e1),
+    ("Y", 1e24),
+    ("Z", 1e21),
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+    ("z", 1e-21),
+    ("y", 1e-24),
+];
+
+/// Powers of 1024, which only data units take, so `MiB` and `MB` differ.
+const BINARY_PREFIXES: &[(&str, f64)] = &[
+    ("Ki", 1024.0),
+    ("Mi", 1048576.0),
+    ("Gi", 1073741824.0),
+    ("Ti", 1099511627776.0),
+    ("Pi", 1125899906842624.0),
+    ("Ei", 1152921504606846976.0),
+];
+
+/// Units durations are split into, with their size in seconds.
+const DURATIONS: &[(&str, i64)] = &[("d", 86400), ("h", 3600), ("min", 60), ("s", 1)];
+
+/// A unit as written, like `km/h`: each name with its power.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Unit {
+    pub terms: Vec<(String, i32)>,
+}
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let term = |(name, power): &(String, i32), power_of: fn(i32) -> i32| match power_of(*power)
+        {
+            1 => name.clone(),
+            p => format!("{}^{}", name, p),
+        };
+        let above: Vec<String> = self
+            .terms
+            .iter()
+            .filter(|(_, p)| *p > 0)
+            .map(|t| term(t, |p| p))
+            .collect();
+        if above.is_empty() {
+            let below: Vec<String> = self.terms.iter().map(|t| term(t, |p| p)).collect();
+            return write!(f, "{}", below.join("*"));
+        }
+        write!(f, "{}", above.join("*"))?;
+        for t in self.terms.iter().filter(|(_, p)| *p < 0) {
+            write!(f, "/{}", term(t, i32::abs))?;
+        }
+        Ok(())
+    }
+}
+impl Unit {
+    /// The product of the units, each power of `other` times `sign`.
+    fn times(&self, other: &Unit, sign: i32) -> Unit {
+        let mut terms = self.terms.clone();
+        for (name, power) in &other.terms {
+            match terms.iter_mut().find(|(n, _)| n == name) {
+                Some((_, p)) => *p += power * sign,
+                None => terms.push((name.clone(), power * sign)),
+            }
+        }
+        terms.retain(|(_, p)| *p != 0);
+        Unit { terms }
+    }
+
+    /// Whether this is one of the units a duration is split into, which
+    /// sums like `1 h 30 min` are written in.
+    pub fn is_duration(&self) -> bool {
+        matches!(self.terms.as_slice(), [(name, 1)] if DURATIONS.iter().any(|(d, _)| d == name))
+    }
+
+    fn name(&self) -> String {
+        match self.terms.is_empty() {
+            true => "1".to_string(),
+            false => self.to_string(),
+        }
+    }
+}
+
+/// A number with a unit, which is `factor` SI base units of `dimension`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Quantity {
+    pub value: Number,
+    pub unit: Unit,
+    factor: f64,
+    dimension: Dimension,
+}
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.parts() {
+            Some(parts) => {
+                let parts: Vec<String> =
+                    parts.iter().map(|(n, u)| format!("{} {}", n, u)).collect();
+                write!(f, "{}", parts.join(" "))
+            }
+            None => write!(f, "{} {}", self.value, self.unit),
+        }
+    }
+}
+impl From<Number> for Quantity {
+    fn from(value: Number) -> Self {
+        Quantity {
+            value,
+            unit: Unit::default(),
+            factor: 1.0,
+            dimension: [0; 8],
+        }
+    }
+}
+impl Quantity {
+    /// `seconds` seconds.
+    pub fn duration(seconds: i64) -> Quantity {
+        Quantity {
+            value: Number::Integer(seconds),
+            unit: Unit {
+                terms: vec![("s".to_string(), 1)],
+            },
+            factor: 1.0,
+            dimension: TIME,
+        }
+    }
+
+    /// The length of a duration in seconds, `None` for other quantities.
+    pub fn in_seconds(&self) -> Option<f64> {
+        (self.dimension == TIME).then(|| self.value.as_f64() * self.factor)
+    }
+
+    pub fn with_value(&self, value: Number) -> Quantity {
+        Quantity {
+            value,
+            ..self.clone()
+        }
+    }
+
+    /// A duration in days, hours or minutes that isn't whole, split like
+    /// `4 h 5 min`, which reads back as the same sum.
+    pub fn parts(&self) -> Option<Vec<(Number, &'static str)>> {
+        let (name, power) = match self.unit.terms.as_slice() {
+            [(name, power)] => (name, *power),
+            _ => return None,
+        };
+        let start = DURATIONS[..3].iter().position(|(d, _)| d == name)?;
+        let value = self.value.as_f64();
+        if power != 1 || self.dimension != TIME || value < 0.0 || value.fract() == 0.0 {
+            return None;
+        }
+        // Rounded to nanoseconds, so float noise doesn't leave a part.
+        let seconds = (value * DURATIONS[start].1 as f64 * 1e9).round() / 1e9;
+        let mut whole = seconds.trunc() as i64;
+        let mut parts = Vec::new();
+        for (unit, size) in &DURATIONS[start..] {
+            let count = whole / size;
+            whole %= size;
+            let count = match *unit == "s" && seconds.fract() != 0.0 {
+                true => Number::Float(count as f64 + seconds.fract()),
+                false => Number::Integer(count),
+            };
+            if count.as_f64() != 0.0 || parts.is_empty() && *unit == "s" {
+                parts.push((count, *unit));
+            }
+        }
+        Some(parts)
+    }
+
+    /// Adding and subtracting need the same dimension, and the result is in
+    /// the unit of `self`. Products of units only cancel when the names do,
+    /// and a dimensionless result is a number.
+    pub fn binary(self, sign: char, other: Quantity, mode: Mode) -> Result<Value, SyaError> {
+        let names = (self.unit.name(), other.unit.name());
+        let incompatible = || SyaError::IncompatibleUnits(names.0.clone(), names.1.clone());
+        let result = match sign {
+            '+' | '-' if self.dimension != other.dimension => return Err(incompatible()),
+            '+' | '-' => {
+                let value = rescale(other.value, other.factor / self.factor, mode)?;
+                self.value
+                    .clone()
+                    .checked_op_in(sign, value, mode)
+                    .map(|v| self.with_value(v))?
+            }
+            '*' | '/' => {
+                let power = if sign == '*' { 1 } else { -1 };
+                let mut dimension = self.dimension;
+                for (d, o) in dimension.iter_mut().zip(other.dimension) {
+                    *d += o * power;
+                }
+                Quantity {
+                    value: self.value.checked_op_in(sign, other.value, mode)?,
+                    unit: self.unit.times(&other.unit, power),
+                    factor: self.factor * other.factor.powi(power),
+                    dimension,
+                }
+            }
+            '^' => {
+                if !other.unit.terms.is_empty() || self.unit.terms.is_empty() {
+                    return Err(incompatible());
+                }
+                let power = match other.value {
+                    Number::Integer(p) => i32::try_from(p).ok(),
+                    Number::Float(p) if p.fract() == 0.0 => Some(p as i32),
+                    _ => None,
+                };
+                let power = power.ok_or_else(|| SyaError::UnitPower(other.value.to_string()))?;
+                let magnitude = Number::Integer(power.unsigned_abs() as i64);
+                let mut value = self.value.checked_op_in('^', magnitude, mode)?;
+                if power < 0 {
+                    value = Number::Integer(1).checked_op_in('/', value, mode)?;
+                }
+                Quantity {
+                    value,
+                    unit: Unit::default().times(&self.unit, power),
+                    factor: self.factor.powi(power),
+                    dimension: self.dimension.map(|d| d * power),
+                }
+            }
+            _ => return Err(SyaError::InvalidOperation(sign)),
+        };
+        result.simplified(mode)
+    }
+
+    fn simplified(self, mode: Mode) -> Result<Value, SyaError> {
+        match self.dimension == [0; 8] {
+            true => Ok(Value::Number(rescale(self.value, self.factor, mode)?)),
+            false => Ok(Value::Quantity(Box::new(self))),
+        }
+    }
+}
+
+/// `value` times `ratio`, exactly when `ratio` is within rounding error of a
+/// fraction that fits.
+fn rescale(value: Number, ratio: f64, mode: Mode) -> Result<Number, SyaError> {
+    if ratio == 1.0 {
+        return Ok(value);
+    }
+    // Sizes are products of floats, so `km/h` is only about 5/18 of `m/s`.
+    let exact = Number::approximate(ratio, ratio.abs() * 1e-14);
+    match exact.map(|r| value.clone().checked_op_in('*', r, Mode::Exact)) {
+        Some(Ok(n)) => n.settle(mode),
+        _ => value.checked_op_in('*', Number::Float(ratio), mode),
+    }
+}
+
+/// The units that can be used, by name.
+#[derive(Debug, Clone)]
+pub struct Units {
+    table: HashMap<String, (f64, Dimension)>,
+}
+impl Default for Units {
+    fn default() -> Self {
+        let mut units = Units {
+            table: HashMap::new(),
+        };
+        for (i, (name, factor)) in BASE.iter().enumerate() {
+            let mut dimension = [0; 8];
+            dimension[i] = 1;
+            units.table.insert(name.to_string(), (*factor, dimension));
+        }
+        for line in DEFINITIONS.lines().filter(|l| !l.is_empty()) {
+            units.define(line).expect("Built-in units should be valid");
+        }
+        units
+    }
+}
+impl Units {
+    /// Adds a unit from a line like `furlong = 201.168 m`.
+    pub fn define(&mut self, line: &str) -> Result<(), SyaError> {
+        let (name, definition) = line
+            .split_once('=')
+            .ok_or_else(|| SyaError::Custom("Expected 'name = value unit'".to_string()))?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+            return Err(SyaError::Custom(format!("Invalid unit name '{}'", name)));
+        }
+        let mut tokenizer = Tokenizer::new(definition);
+        let (value, unit) = match tokenizer.parse()?.as_slice() {
+            [Token::Number(n)] => (n.clone(), Unit::default()),
+            [Token::Number(n), Token::Unit(unit)] => (n.clone(), unit.clone()),
+            _ => {
+                return Err(SyaError::Custom(format!(
+                    "Expected a number and a unit, got '{}'",
+                    definition.trim()
+                )))
+            }
+        };
+        let (factor, dimension) = self.measure(&unit)?;
+        self.table
+            .insert(name.to_string(), (value.as_f64() * factor, dimension));
+        Ok(())
+    }
+
+    /// Size and dimension of a unit name, with or without a prefix. Whole
+    /// names are matched first, so `min` is a minute.
+    fn get(&self, name: &str) -> Option<(f64, Dimension)> {
+        if let Some(&unit) = self.table.get(name) {
+            return Some(unit);
+        }
+        let prefixed = |(prefix, scale): &(&str, f64)| {
+            let (factor, dimension) = self.table.get(name.strip_prefix(prefix)?)?;
+            Some((factor * scale, *dimension))
+        };
+        PREFIXES.iter().find_map(prefixed).or_else(|| {
+            BINARY_PREFIXES
+                .iter()
+                .find_map(prefixed)
+                .filter(|(_, dimension)| *dimension == DATA)
+        })
+    }
+
+    /// Whether `name` is a unit, with or without a prefix.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn measure(&self, unit: &Unit) -> Result<(f64, Dimension), SyaError> {
+        let mut total = (1.0, [0; 8]);
+        for (name, power) in &unit.terms {
+            let (factor, dimension) = self
+                .get(name)
+                .ok_or_else(|| SyaError::UnknownUnit(name.clone()))?;
+            total.0 *= factor.powi(*power);
+            for (d, u) in total.1.iter_mut().zip(dimension) {
+                *d += u * power;
+            }
+        }
+        Ok(total)
+    }
+
+    /// One of `unit`.
+    pub fn one(&self, unit: &Unit) -> Result<Quantity, SyaError> {
+        let (factor, dimension) = self.measure(unit)?;
+        Ok(Quantity {
+            value: Number::Integer(1),
+            unit: unit.clone(),
+            factor,
+            dimension,
+        })
+    }
+
+    /// Converts `value` to `unit`, which must have the same dimension.
+    /// Symbolic values stay symbolic.
+    pub fn convert(&self, value: Value, unit: &Unit, mode: Mode) -> Result<Value, SyaError> {
+        let from = match value {
+            Value::Number(n) => Quantity::from(n),
+            Value::Quantity(q) => *q,
+            Value::Expr(e) => return Ok(Value::Expr(Expr::Convert(Box::new(e), unit.clone()))),
+            Value::Date(_) => {
+                return Err(SyaError::IncompatibleUnits("date".to_string(), unit.name()))
+            }
+            Value::List(items) => {
+                return items
+                    .into_iter()
+                    .map(|v| self.convert(v, unit, mode))
+                    .collect::<Result<_, _>>()
+                    .map(Value::List)
+            }
+        };
+        let to = self.one(unit)?;
+        if from.dimension != to.dimension {
+            return Err(SyaError::IncompatibleUnits(
+                from.unit.name(),
+                to.unit.name(),
+            ));
+        }
+        let value = rescale(from.value, from.factor / to.factor, mode)?;
+        Ok(Value::Quantity(Box::new(to.with_value(value))))
+    }
+
+    /// `value` in the unit of its kind that gives the shortest number, for
+    /// durations, data sizes and data rates. Data stays in bytes or bits and
+    /// in binary or decimal prefixes, whichever `value` has.
+    pub fn readable(&self, value: Value, mode: Mode) -> Result<Value, SyaError> {
+        let q = match value {
+            Value::Quantity(q) => *q,
+            Value::List(items) => {
+                return items
+                    .into_iter()
+                    .map(|v| self.readable(v, mode))
+                    .collect::<Result<_, _>>()
+                    .map(Value::List)
+            }
+            v => return Ok(v),
+        };
+        let size = (q.value.as_f64() * q.factor).abs();
+        let scale = match scale(&q) {
+            Some(scale) if size.is_normal() => scale,
+            _ => return Ok(Value::Quantity(Box::new(q))),
+        };
+        let mut best = &scale[0];
+        for unit in &scale {
+            if size * (1.0 + 1e-12) >= self.measure(unit)?.0 {
+                best = unit;
+            }
+        }
+        self.convert(Value::Quantity(Box::new(q)), best, mode)
+    }
+}
+
+/// The units a quantity of its kind can be shown in, smallest first.
+fn scale(q: &Quantity) -> Option<Vec<Unit>> {
+    let names: Vec<String> = match q.dimension {
+        TIME => {
+            return Some(
+                ["ns", "us", "ms", "s", "min", "h", "d"]
+                    .iter()
+                    .map(|name| Unit {
+                        terms: vec![(name.to_string(), 1)],
+                    })
+                    .collect(),
+            )
+        }
+        DATA | RATE => {
+            let data: Vec<(&str, bool)> = q
+                .unit
+                .terms
+                .iter()
+                .filter_map(|(name, _)| data_prefix(name))
+                .collect();
+            let binary = data.iter().any(|(prefix, _)| prefix.ends_with('i'));
+            let base = match data.iter().all(|(_, bits)| *bits) {
+                true => "bit",
+                false => "B",
+            };
+            let prefixes = match binary {
+                true => ["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"],
+                false => ["", "k", "M", "G", "T", "P", "E"],
+            };
+            prefixes.iter().map(|p| format!("{}{}", p, base)).collect()
+        }
+        _ => return None,
+    };
+    let per_second = q.dimension == RATE;
+    Some(
+        names
+            .into_iter()
+            .map(|name| {
+                let mut terms = vec![(name, 1)];
+                if per_second {
+                    terms.push(("s".to_string(), -1));
+                }
+                Unit { terms }
+            })
+            .collect(),
+    )
+}
+
+/// The prefix of a byte or bit unit name, and whether it's bits.
+fn data_prefix(name: &str) -> Option<(&str, bool)> {
+    let (prefix, bits) = match name.strip_suffix("bit") {
+        Some(prefix) => (prefix, true),
+        None => (name.strip_suffix('B')?, false),
+    };
+    let known = prefix.is_empty()
+        || PREFIXES
+            .iter()
+            .chain(BINARY_PREFIXES)
+            .any(|(p, _)| *p == prefix);
+    known.then_some((prefix, bits))
+}
+
+pub fn path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("units"))
+}
+
+/// The built-in units with the ones from the units file, which has lines
+/// like `furlong = 201.168 m`.
+pub fn load() -> Result<Units, String> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Units::default()),
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => parse(&content).map_err(|e| format!("{}:{}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Units::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Units, String> {
+    let mut units = Units::default();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        units
+            .define(line)
+            .map_err(|e| format!("{}: {}", number + 1, e))?;
+    }
+    Ok(units)
+}
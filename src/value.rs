@@ -0,0 +1,132 @@
+use core::fmt;
+
+use crate::{
+    dates::Date,
+    errors::SyaError,
+    expr::Expr,
+    matrix,
+    number::{Mode, Number},
+    simplify::{simplify, SimplifyOptions},
+    units::Quantity,
+};
+
+/// A value on the evaluation stack. Symbolic results, like the ones from
+/// `diff`, stay symbolic when combined with other values.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Number(Number),
+    Expr(Expr),
+    /// Operators apply to each element, and combine two lists element by
+    /// element.
+    List(Vec<Value>),
+    /// A number with a unit, boxed to keep values small on deep stacks.
+    Quantity(Box<Quantity>),
+    Date(Date),
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Expr(e) => write!(f, "{}", e),
+            Value::List(items) => write!(f, "{}", list(items, |v| v.to_string())),
+            Value::Quantity(q) => write!(f, "{}", q),
+            Value::Date(d) => write!(f, "{}", d),
+        }
+    }
+}
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Value::Number(n)
+    }
+}
+impl Value {
+    pub fn into_expr(self) -> Expr {
+        match self {
+            Value::Number(n) => Expr::Number(n),
+            Value::Expr(e) => e,
+            Value::List(items) => Expr::Call(
+                "list".to_string(),
+                items.into_iter().map(Value::into_expr).collect(),
+            ),
+            Value::Quantity(q) => Expr::Quantity(Box::new(Expr::Number(q.value)), q.unit),
+            Value::Date(d) => Expr::Date(d),
+        }
+    }
+
+    pub fn unary(self, sign: char) -> Result<Self, SyaError> {
+        match (sign, self) {
+            ('+', v) => Ok(v),
+            ('-', Value::Number(n)) => Ok(Value::Number(n.negate())),
+            ('-', Value::Quantity(q)) => Ok(Value::Quantity(Box::new(
+                q.with_value(q.value.clone().negate()),
+            ))),
+            ('-', Value::Expr(e)) => Ok(symbolic(Expr::Unary(sign, Box::new(e)))),
+            (_, Value::List(items)) => items
+                .into_iter()
+                .map(|v| v.unary(sign))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            _ => Err(SyaError::WrongUnary(sign)),
+        }
+    }
+
+    pub fn binary(self, sign: char, other: Self, mode: Mode) -> Result<Self, SyaError> {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => {
+                Ok(Value::Number(a.checked_op_in(sign, b, mode)?))
+            }
+            (a, b) if sign == '*' && matrix::is_matrix(&a) && matrix::is_matrix(&b) => {
+                matrix::matmul(&a, &b, mode)
+            }
+            (Value::List(a), Value::List(b)) if a.len() != b.len() => {
+                Err(SyaError::LengthMismatch(a.len(), b.len()))
+            }
+            (Value::List(a), Value::List(b)) => a
+                .into_iter()
+                .zip(b)
+                .map(|(a, b)| a.binary(sign, b, mode))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            (Value::List(a), b) => a
+                .into_iter()
+                .map(|a| a.binary(sign, b.clone(), mode))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            (a, Value::List(b)) => b
+                .into_iter()
+                .map(|b| a.clone().binary(sign, b, mode))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            (Value::Date(a), b @ (Value::Date(_) | Value::Quantity(_) | Value::Number(_))) => {
+                a.binary(sign, b)
+            }
+            (Value::Quantity(a), Value::Date(b)) if sign == '+' => {
+                b.binary(sign, Value::Quantity(a))
+            }
+            (Value::Number(_) | Value::Quantity(_), Value::Date(_)) => {
+                Err(SyaError::InvalidOperation(sign))
+            }
+            (Value::Quantity(a), Value::Quantity(b)) => a.binary(sign, *b, mode),
+            (Value::Quantity(a), Value::Number(b)) => a.binary(sign, b.into(), mode),
+            (Value::Number(a), Value::Quantity(b)) => Quantity::from(a).binary(sign, *b, mode),
+            (a, b) => Ok(symbolic(Expr::Binary(
+                sign,
+                Box::new(a.into_expr()),
+                Box::new(b.into_expr()),
+            ))),
+        }
+    }
+}
+
+/// Writes `items` like `[1, 2, 3]`.
+pub fn list<T>(items: &[T], show: impl Fn(&T) -> String) -> String {
+    let items: Vec<String> = items.iter().map(show).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn symbolic(e: Expr) -> Value {
+    match simplify(e, &SimplifyOptions::default()) {
+        Expr::Number(n) => Value::Number(n),
+        e => Value::Expr(e),
+    }
+}